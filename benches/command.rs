@@ -0,0 +1,112 @@
+// Compares the three command dispatch styles from `command.rs` on schemas of the same size:
+// `Box<dyn Migration>`, plain `fn` pointers and boxed `Fn` closures.
+//
+// Each benchmark builds or executes `COMMANDS` commands that alternate between two
+// migrations, so every variant does the same amount of work. Commands hand back pre-built
+// `&'static str`s where the variant allows it. `function_pointer` commands are typed
+// `fn() -> String` and so allocate once per call; keep that in mind when comparing its
+// `execute` numbers against the others.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::hint::black_box;
+
+use design_patterns::patterns::command::{fn_trait_object, function_pointer, trait_object};
+
+// The crate's own `CreateTable`/`AddField` render DDL; these return fixed strings like the
+// commands of the other variants.
+design_patterns::impl_migration!(CreateTable, "create table", "drop table");
+design_patterns::impl_migration!(AddField, "add field", "remove field");
+
+const COMMANDS: usize = 1_000;
+
+fn create_table() -> String {
+    String::from("create table")
+}
+
+fn drop_table() -> String {
+    String::from("drop table")
+}
+
+fn add_field() -> String {
+    String::from("add field")
+}
+
+fn remove_field() -> String {
+    String::from("remove field")
+}
+
+fn trait_object_schema() -> trait_object::Schema {
+    let mut schema = trait_object::Schema::new();
+
+    for i in 0..COMMANDS {
+        if i % 2 == 0 {
+            schema.add_migration(Box::new(CreateTable));
+        } else {
+            schema.add_migration(Box::new(AddField));
+        }
+    }
+
+    schema
+}
+
+fn function_pointer_schema() -> function_pointer::Schema {
+    let mut schema = function_pointer::Schema::new();
+
+    for i in 0..COMMANDS {
+        if i % 2 == 0 {
+            schema.add_migration(create_table, drop_table);
+        } else {
+            schema.add_migration(add_field, remove_field);
+        }
+    }
+
+    schema
+}
+
+fn fn_trait_object_schema() -> fn_trait_object::Schema<'static> {
+    let mut schema = fn_trait_object::Schema::new();
+
+    for i in 0..COMMANDS {
+        if i % 2 == 0 {
+            schema.add_migration(|| "create table", || "drop table");
+        } else {
+            schema.add_migration(|| "add field", || "remove field");
+        }
+    }
+
+    schema
+}
+
+fn execute(c: &mut Criterion) {
+    let mut group = c.benchmark_group("command/execute");
+    group.throughput(Throughput::Elements(COMMANDS as u64));
+
+    let schema = trait_object_schema();
+    group.bench_function("trait_object", |b| b.iter(|| black_box(&schema).execute()));
+
+    let schema = function_pointer_schema();
+    group.bench_function("function_pointer", |b| {
+        b.iter(|| black_box(&schema).execute())
+    });
+
+    let schema = fn_trait_object_schema();
+    group.bench_function("fn_trait_object", |b| {
+        b.iter(|| black_box(&schema).execute())
+    });
+
+    group.finish();
+}
+
+fn add_migration(c: &mut Criterion) {
+    let mut group = c.benchmark_group("command/add_migration");
+    group.throughput(Throughput::Elements(COMMANDS as u64));
+
+    group.bench_function("trait_object", |b| b.iter(trait_object_schema));
+    group.bench_function("function_pointer", |b| b.iter(function_pointer_schema));
+    group.bench_function("fn_trait_object", |b| b.iter(fn_trait_object_schema));
+
+    group.finish();
+}
+
+criterion_group!(benches, execute, add_migration);
+criterion_main!(benches);
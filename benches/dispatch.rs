@@ -0,0 +1,82 @@
+// Backs the claim at the top of `command.rs`: the same schema of migrations executed through
+// `Box<dyn Migration>` (dynamic dispatch) and through plain `fn` pointers.
+//
+// Both sides produce owned `String`s so the only difference measured is how each command is
+// called, not whether its output is allocated.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+use design_patterns::patterns::command::{function_pointer, trait_object};
+
+// The crate's own `CreateTable`/`AddField` render DDL; these return fixed strings like the
+// commands of the other variants.
+design_patterns::impl_migration!(CreateTable, "create table", "drop table");
+design_patterns::impl_migration!(AddField, "add field", "remove field");
+
+const COMMANDS: usize = 4_000;
+
+fn create_table() -> String {
+    String::from("create table")
+}
+
+fn drop_table() -> String {
+    String::from("drop table")
+}
+
+fn add_field() -> String {
+    String::from("add field")
+}
+
+fn remove_field() -> String {
+    String::from("remove field")
+}
+
+fn trait_object_schema() -> trait_object::Schema {
+    (0..COMMANDS)
+        .map(|i| -> Box<dyn trait_object::Migration> {
+            if i % 2 == 0 {
+                Box::new(CreateTable)
+            } else {
+                Box::new(AddField)
+            }
+        })
+        .collect()
+}
+
+fn function_pointer_schema() -> function_pointer::Schema {
+    (0..COMMANDS)
+        .map(|i| -> (function_pointer::FnPtr, function_pointer::FnPtr) {
+            if i % 2 == 0 {
+                (create_table, drop_table)
+            } else {
+                (add_field, remove_field)
+            }
+        })
+        .collect()
+}
+
+fn execute(c: &mut Criterion) {
+    let mut group = c.benchmark_group("execute");
+
+    let schema = trait_object_schema();
+    group.bench_function("trait_object", |b| {
+        b.iter(|| {
+            black_box(&schema)
+                .execute()
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let schema = function_pointer_schema();
+    group.bench_function("function_pointer", |b| {
+        b.iter(|| black_box(&schema).execute())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, execute);
+criterion_main!(benches);
@@ -1 +1,37 @@
-fn main() {}
+use std::io;
+
+use design_patterns::patterns::command::ddl::{ColumnDef, SqlType};
+use design_patterns::patterns::command::trait_object::{AddField, CreateTable, Schema};
+
+fn main() -> io::Result<()> {
+    match std::env::args().nth(1).as_deref() {
+        Some("migrate") => migrate(),
+        _ => Ok(()),
+    }
+}
+
+fn migrate() -> io::Result<()> {
+    let mut schema = Schema::new();
+
+    schema.add_migration(Box::new(CreateTable::new(
+        "users",
+        vec![
+            ColumnDef::new("id", SqlType::Integer),
+            ColumnDef::new("name", SqlType::Text),
+        ],
+    )));
+    schema.add_migration(Box::new(AddField::new(
+        "users",
+        ColumnDef::new("active", SqlType::Boolean),
+    )));
+    schema.add_migration(Box::new(CreateTable::new(
+        "order",
+        vec![
+            ColumnDef::new("id", SqlType::Integer),
+            ColumnDef::new("user_id", SqlType::Integer),
+        ],
+    )));
+
+    schema.run_interactive(io::stdin().lock(), &mut io::stdout())?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+// A factory produces a whole family of widgets, so a caller holding one factory can't end up
+// mixing a light button with a dark checkbox.
+
+pub trait Button {
+    fn render(&self) -> String;
+}
+
+pub trait Checkbox {
+    fn render(&self) -> String;
+}
+
+pub trait WidgetFactory {
+    fn create_button(&self, label: &str) -> Box<dyn Button>;
+    fn create_checkbox(&self, checked: bool) -> Box<dyn Checkbox>;
+}
+
+struct LightButton {
+    label: String,
+}
+
+impl Button for LightButton {
+    fn render(&self) -> String {
+        format!("[light] button({})", self.label)
+    }
+}
+
+struct LightCheckbox {
+    checked: bool,
+}
+
+impl Checkbox for LightCheckbox {
+    fn render(&self) -> String {
+        format!("[light] checkbox({})", if self.checked { "x" } else { " " })
+    }
+}
+
+struct DarkButton {
+    label: String,
+}
+
+impl Button for DarkButton {
+    fn render(&self) -> String {
+        format!("[dark] button({})", self.label)
+    }
+}
+
+struct DarkCheckbox {
+    checked: bool,
+}
+
+impl Checkbox for DarkCheckbox {
+    fn render(&self) -> String {
+        format!("[dark] checkbox({})", if self.checked { "x" } else { " " })
+    }
+}
+
+pub struct LightThemeFactory;
+
+impl WidgetFactory for LightThemeFactory {
+    fn create_button(&self, label: &str) -> Box<dyn Button> {
+        Box::new(LightButton {
+            label: String::from(label),
+        })
+    }
+
+    fn create_checkbox(&self, checked: bool) -> Box<dyn Checkbox> {
+        Box::new(LightCheckbox { checked })
+    }
+}
+
+pub struct DarkThemeFactory;
+
+impl WidgetFactory for DarkThemeFactory {
+    fn create_button(&self, label: &str) -> Box<dyn Button> {
+        Box::new(DarkButton {
+            label: String::from(label),
+        })
+    }
+
+    fn create_checkbox(&self, checked: bool) -> Box<dyn Checkbox> {
+        Box::new(DarkCheckbox { checked })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::patterns::abstract_factory::{DarkThemeFactory, LightThemeFactory, WidgetFactory};
+
+    fn render_form(factory: &dyn WidgetFactory) -> Vec<String> {
+        vec![
+            factory.create_button("OK").render(),
+            factory.create_checkbox(true).render(),
+        ]
+    }
+
+    #[test]
+    fn test_dark_theme() {
+        let widgets = render_form(&DarkThemeFactory);
+
+        assert_eq!(widgets, vec!["[dark] button(OK)", "[dark] checkbox(x)"]);
+        assert!(widgets.iter().all(|widget| widget.starts_with("[dark]")));
+    }
+
+    #[test]
+    fn test_light_theme() {
+        let widgets = render_form(&LightThemeFactory);
+
+        assert!(widgets.iter().all(|widget| widget.starts_with("[light]")));
+    }
+}
@@ -0,0 +1,88 @@
+// The abstraction (`Shape`) and the implementation (`Renderer`) vary independently:
+// adding a renderer doesn't touch the shapes and adding a shape doesn't touch the renderers.
+
+pub trait Renderer {
+    fn render_circle(&self, radius: f64) -> String;
+    fn render_square(&self, side: f64) -> String;
+}
+
+pub struct VectorRenderer;
+
+impl Renderer for VectorRenderer {
+    fn render_circle(&self, radius: f64) -> String {
+        format!("Drawing a circle of radius {} as vectors", radius)
+    }
+
+    fn render_square(&self, side: f64) -> String {
+        format!("Drawing a square of side {} as vectors", side)
+    }
+}
+
+pub struct RasterRenderer;
+
+impl Renderer for RasterRenderer {
+    fn render_circle(&self, radius: f64) -> String {
+        format!("Drawing a circle of radius {} as pixels", radius)
+    }
+
+    fn render_square(&self, side: f64) -> String {
+        format!("Drawing a square of side {} as pixels", side)
+    }
+}
+
+pub enum ShapeKind {
+    Circle { radius: f64 },
+    Square { side: f64 },
+}
+
+pub struct Shape<'a> {
+    kind: ShapeKind,
+    renderer: &'a dyn Renderer,
+}
+
+impl<'a> Shape<'a> {
+    pub fn circle(radius: f64, renderer: &'a dyn Renderer) -> Self {
+        Shape {
+            kind: ShapeKind::Circle { radius },
+            renderer,
+        }
+    }
+
+    pub fn square(side: f64, renderer: &'a dyn Renderer) -> Self {
+        Shape {
+            kind: ShapeKind::Square { side },
+            renderer,
+        }
+    }
+
+    pub fn draw(&self) -> String {
+        match self.kind {
+            ShapeKind::Circle { radius } => self.renderer.render_circle(radius),
+            ShapeKind::Square { side } => self.renderer.render_square(side),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::patterns::bridge::{RasterRenderer, Shape, VectorRenderer};
+
+    #[test]
+    fn test_bridge() {
+        let vector = VectorRenderer;
+        let raster = RasterRenderer;
+
+        assert_eq!(
+            Shape::circle(5.0, &vector).draw(),
+            "Drawing a circle of radius 5 as vectors"
+        );
+        assert_eq!(
+            Shape::circle(5.0, &raster).draw(),
+            "Drawing a circle of radius 5 as pixels"
+        );
+        assert_eq!(
+            Shape::square(2.0, &raster).draw(),
+            "Drawing a square of side 2 as pixels"
+        );
+    }
+}
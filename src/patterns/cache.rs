@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Entries live for `ttl` after they were put and are evicted when read after that. The clock
+/// is injected so callers can choose the time source and tests can move time forward.
+///
+/// A `ttl` too large to add to the clock, such as `Duration::MAX`, means entries never expire.
+pub struct Cache<K, V, C: Fn() -> Instant = fn() -> Instant> {
+    // `None` when the expiry is past anything an `Instant` can represent.
+    entries: HashMap<K, (V, Option<Instant>)>,
+    ttl: Duration,
+    clock: C,
+}
+
+impl<K: Eq + Hash, V> Cache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Cache::with_clock(ttl, Instant::now)
+    }
+}
+
+impl<K: Eq + Hash, V, C: Fn() -> Instant> Cache<K, V, C> {
+    pub fn with_clock(ttl: Duration, clock: C) -> Self {
+        Cache {
+            entries: HashMap::new(),
+            ttl,
+            clock,
+        }
+    }
+
+    /// Stores `value`, replacing any previous one and restarting its TTL.
+    pub fn put(&mut self, key: K, value: V) {
+        let expires_at = (self.clock)().checked_add(self.ttl);
+        self.entries.insert(key, (value, expires_at));
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let now = (self.clock)();
+
+        if self
+            .entries
+            .get(key)
+            .is_some_and(|(_, expires_at)| expired(*expires_at, now))
+        {
+            self.entries.remove(key);
+        }
+
+        self.entries.get(key).map(|(value, _)| value)
+    }
+
+    /// Number of entries that haven't expired yet.
+    pub fn len(&self) -> usize {
+        let now = (self.clock)();
+
+        self.entries
+            .values()
+            .filter(|(_, expires_at)| !expired(*expires_at, now))
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn expired(expires_at: Option<Instant>, now: Instant) -> bool {
+    expires_at.is_some_and(|expires_at| expires_at <= now)
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    use crate::patterns::cache::Cache;
+
+    #[test]
+    fn test_cache() {
+        let mut cache = Cache::new(Duration::from_secs(60));
+
+        cache.put("answer", 42);
+
+        assert_eq!(cache.get(&"answer"), Some(&42));
+        assert_eq!(cache.get(&"question"), None);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_cache_expires() {
+        let now = Rc::new(Cell::new(Instant::now()));
+        let clock = Rc::clone(&now);
+        let mut cache = Cache::with_clock(Duration::from_secs(60), move || clock.get());
+
+        cache.put("answer", 42);
+        now.set(now.get() + Duration::from_secs(30));
+        cache.put("question", 7);
+        assert_eq!(cache.get(&"answer"), Some(&42));
+
+        now.set(now.get() + Duration::from_secs(31));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&"answer"), None);
+        assert_eq!(cache.get(&"question"), Some(&7));
+
+        now.set(now.get() + Duration::from_secs(60));
+        assert!(cache.is_empty());
+        assert_eq!(cache.get(&"question"), None);
+    }
+
+    #[test]
+    fn test_cache_unbounded_ttl() {
+        let now = Rc::new(Cell::new(Instant::now()));
+        let clock = Rc::clone(&now);
+        let mut cache = Cache::with_clock(Duration::MAX, move || clock.get());
+
+        cache.put("answer", 42);
+        now.set(now.get() + Duration::from_secs(365 * 24 * 60 * 60));
+
+        assert_eq!(cache.get(&"answer"), Some(&42));
+        assert_eq!(cache.len(), 1);
+    }
+}
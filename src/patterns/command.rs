@@ -1,226 +1,6908 @@
 // As performance, there is always a trade-off between performance and code simplicity and organisation.
 // Static dispatch gives faster performance, while dynamic dispatch provides flexibility when we structure our application
 
-/// If command is a whole struct with a bunch of functions and variables defined as separated module
-/// then using this would be more suitable
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    IndexOutOfRange { index: usize, len: usize },
+    UnknownDependency { name: String, dependency: String },
+    Cycle { members: Vec<String> },
+    InvalidVersion { version: u64 },
+    DuplicateVersion { version: u64 },
+    DuplicateKey { key: String },
+    InvalidHistory { reason: String },
+    StaleCheckpoint,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::IndexOutOfRange { index, len } => {
+                write!(f, "index {} is out of range for {} migrations", index, len)
+            }
+            SchemaError::UnknownDependency { name, dependency } => {
+                write!(f, "{} depends on unknown migration {}", name, dependency)
+            }
+            SchemaError::Cycle { members } => {
+                write!(f, "dependency cycle between {}", members.join(" -> "))
+            }
+            SchemaError::InvalidVersion { version } => {
+                write!(f, "version {} is reserved for the empty schema", version)
+            }
+            SchemaError::DuplicateVersion { version } => {
+                write!(f, "version {} is already registered", version)
+            }
+            SchemaError::DuplicateKey { key } => {
+                write!(f, "key {} is already registered", key)
+            }
+            SchemaError::InvalidHistory { reason } => {
+                write!(f, "invalid migration history: {}", reason)
+            }
+            SchemaError::StaleCheckpoint => {
+                write!(f, "checkpoint is stale: the schema has been executed since")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    Failed {
+        name: String,
+        reason: String,
+    },
+    ChecksumMismatch {
+        name: String,
+        expected: u64,
+        actual: u64,
+    },
+    TimedOut {
+        name: String,
+    },
+    /// A failure worth retrying, such as a lost connection or a lock timeout.
+    Transient {
+        name: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Failed { name, reason } => {
+                write!(f, "migration {} failed: {}", name, reason)
+            }
+            MigrationError::ChecksumMismatch {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "migration {} changed after it was applied: checksum {:016x} is now {:016x}",
+                name, expected, actual
+            ),
+            MigrationError::TimedOut { name } => write!(f, "migration {} timed out", name),
+            MigrationError::Transient { name, reason } => {
+                write!(f, "migration {} failed transiently: {}", name, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// Builds a schema in one expression. A list of migrations gives a `trait_object::Schema`,
+/// boxing each one, while `up:`/`down:` closure pairs give a `fn_mut::Schema`.
 ///
 /// # Example
 /// ```
-/// use design_patterns::patterns::command::trait_object::{Schema, CreateTable, AddField};
+/// use design_patterns::schema;
+/// use design_patterns::patterns::command::ddl::{ColumnDef, SqlType};
+/// use design_patterns::patterns::command::trait_object::{AddField, CreateTable};
 ///
-/// let mut schema = Schema::default();
-/// let cmd = Box::new(CreateTable);
+/// let schema = schema![
+///     CreateTable::new("users", vec![ColumnDef::new("id", SqlType::Integer)]),
+///     AddField::new("users", ColumnDef::new("email", SqlType::Text)),
+/// ];
+/// assert_eq!(
+///     vec!["CREATE TABLE users (id INTEGER);", "ALTER TABLE users ADD COLUMN email TEXT;"],
+///     schema.execute()
+/// );
 ///
-/// schema.add_migration(cmd);
+/// let mut schema = schema! {
+///     up: || "create table".into(), down: || "drop table".into();
+///     up: || "add field".into(), down: || "remove field".into();
+/// };
+/// assert_eq!(vec!["remove field", "drop table"], schema.rollback());
+/// ```
+#[macro_export]
+macro_rules! schema {
+    ($(up: $up:expr, down: $down:expr);+ $(;)?) => {{
+        let mut schema = $crate::patterns::command::fn_mut::Schema::new();
+        $(schema.add_migration($up, $down);)+
+        schema
+    }};
+    ($($cmd:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut schema = $crate::patterns::command::trait_object::Schema::new();
+        $(schema.add_migration(Box::new($cmd));)*
+        schema
+    }};
+}
+
+/// Declares a `trait_object::Migration` without the impl boilerplate, named after the struct.
+/// `Name, execute, rollback` gives a unit struct returning the two strings. Listing fields
+/// gives a struct built with `Name::new(fields...)`, which renders both closures once up
+/// front because migrations hand out borrowed strings.
 ///
-/// let cmd = Box::new(AddField);
+/// # Example
+/// ```
+/// use design_patterns::impl_migration;
+/// use design_patterns::patterns::command::trait_object::{Migration, Schema};
 ///
-/// schema.add_migration(cmd);
+/// impl_migration!(CreateUsers, "create table users", "drop table users");
+/// impl_migration!(
+///     AddIndex { table: String },
+///     |s| format!("add index on {}", s.table),
+///     |s| format!("drop index on {}", s.table),
+/// );
 ///
-/// assert_eq!(vec!["create table", "add field"], schema.execute());
-/// assert_eq!(vec!["remove field", "drop table"], schema.rollback());
+/// let mut schema = Schema::new();
+/// schema.add_migration(Box::new(CreateUsers));
+/// schema.add_migration(Box::new(AddIndex::new(String::from("users"))));
+///
+/// assert_eq!(vec!["create table users", "add index on users"], schema.execute());
+/// assert_eq!("AddIndex", AddIndex::new(String::from("users")).name());
 /// ```
-pub mod trait_object {
-    pub trait Migration {
-        fn execute(&self) -> &str;
-        fn rollback(&self) -> &str;
-    }
+#[macro_export]
+macro_rules! impl_migration {
+    ($name:ident, $execute:expr, $rollback:expr $(,)?) => {
+        #[derive(Clone)]
+        pub struct $name;
 
-    pub struct CreateTable;
-    impl Migration for CreateTable {
-        fn execute(&self) -> &str {
-            "create table"
+        impl $crate::patterns::command::trait_object::Migration for $name {
+            fn execute(&self) -> &str {
+                $execute
+            }
+
+            fn rollback(&self) -> &str {
+                $rollback
+            }
+
+            fn name(&self) -> &str {
+                stringify!($name)
+            }
+        }
+    };
+    ($name:ident { $($field:ident: $ty:ty),* $(,)? }, $execute:expr, $rollback:expr $(,)?) => {
+        #[derive(Clone)]
+        pub struct $name {
+            $(pub $field: $ty,)*
+            rendered: (String, String),
         }
 
-        fn rollback(&self) -> &str {
-            "drop table"
+        impl $name {
+            pub fn new($($field: $ty),*) -> Self {
+                // Gives the closures their parameter type, which a direct call can't infer.
+                fn render(cmd: &$name, f: impl Fn(&$name) -> String) -> String {
+                    f(cmd)
+                }
+
+                let mut cmd = $name {
+                    $($field,)*
+                    rendered: (String::new(), String::new()),
+                };
+                cmd.rendered = (render(&cmd, $execute), render(&cmd, $rollback));
+                cmd
+            }
+        }
+
+        impl $crate::patterns::command::trait_object::Migration for $name {
+            fn execute(&self) -> &str {
+                &self.rendered.0
+            }
+
+            fn rollback(&self) -> &str {
+                &self.rendered.1
+            }
+
+            fn name(&self) -> &str {
+                stringify!($name)
+            }
         }
+    };
+}
+
+// Just enough JSON to persist a migration history without pulling in a serialisation crate.
+mod json {
+    use std::iter::Peekable;
+    use std::str::Chars;
+
+    #[derive(Debug, PartialEq)]
+    pub enum Value {
+        Null,
+        Number(u64),
+        String(String),
+        Array(Vec<Value>),
+        Object(Vec<(String, Value)>),
     }
 
-    pub struct AddField;
-    impl Migration for AddField {
-        fn execute(&self) -> &str {
-            "add field"
+    impl Value {
+        pub fn get(&self, key: &str) -> Option<&Value> {
+            match self {
+                Value::Object(fields) => fields
+                    .iter()
+                    .find(|(field, _)| field == key)
+                    .map(|(_, value)| value),
+                _ => None,
+            }
         }
+    }
 
-        fn rollback(&self) -> &str {
-            "remove field"
+    pub fn escape(value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len() + 2);
+
+        escaped.push('"');
+        for c in value.chars() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+                c => escaped.push(c),
+            }
         }
+        escaped.push('"');
+
+        escaped
     }
 
-    pub struct Schema {
-        commands: Vec<Box<dyn Migration>>,
+    pub fn parse(input: &str) -> Result<Value, String> {
+        let mut parser = Parser {
+            chars: input.chars().peekable(),
+        };
+
+        let value = parser.value()?;
+        parser.skip_whitespace();
+
+        match parser.chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected trailing {:?}", c)),
+        }
     }
-    impl Schema {
-        pub fn new() -> Self {
-            Schema {
-                commands: Vec::new(),
+
+    struct Parser<'a> {
+        chars: Peekable<Chars<'a>>,
+    }
+
+    impl Parser<'_> {
+        fn skip_whitespace(&mut self) {
+            while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        }
+
+        fn expect(&mut self, expected: char) -> Result<(), String> {
+            self.skip_whitespace();
+
+            match self.chars.next() {
+                Some(c) if c == expected => Ok(()),
+                Some(c) => Err(format!("expected {:?}, found {:?}", expected, c)),
+                None => Err(format!("expected {:?}, found end of input", expected)),
             }
         }
 
-        pub fn add_migration(&mut self, cmd: Box<dyn Migration>) {
-            self.commands.push(cmd);
+        fn value(&mut self) -> Result<Value, String> {
+            self.skip_whitespace();
+
+            match self.chars.peek() {
+                Some('n') => {
+                    for expected in "null".chars() {
+                        self.expect(expected)?;
+                    }
+                    Ok(Value::Null)
+                }
+                Some('"') => self.string().map(Value::String),
+                Some('[') => self.array(),
+                Some('{') => self.object(),
+                Some(c) if c.is_ascii_digit() => self.number(),
+                Some(c) => Err(format!("unexpected {:?}", c)),
+                None => Err(String::from("unexpected end of input")),
+            }
         }
 
-        pub fn execute(&self) -> Vec<&str> {
-            self.commands.iter().map(|cmd| cmd.execute()).collect()
+        fn number(&mut self) -> Result<Value, String> {
+            let mut digits = String::new();
+
+            while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit()) {
+                digits.push(c);
+            }
+
+            digits
+                .parse()
+                .map(Value::Number)
+                .map_err(|err| format!("invalid number {}: {}", digits, err))
         }
 
-        pub fn rollback(&self) -> Vec<&str> {
-            self.commands
-                .iter()
-                .rev()
-                .map(|cmd| cmd.rollback())
-                .collect()
+        fn string(&mut self) -> Result<String, String> {
+            self.expect('"')?;
+            let mut value = String::new();
+
+            loop {
+                match self.chars.next() {
+                    Some('"') => return Ok(value),
+                    Some('\\') => match self.chars.next() {
+                        Some('"') => value.push('"'),
+                        Some('\\') => value.push('\\'),
+                        Some('/') => value.push('/'),
+                        Some('n') => value.push('\n'),
+                        Some('r') => value.push('\r'),
+                        Some('t') => value.push('\t'),
+                        Some('u') => {
+                            let code: String = self.chars.by_ref().take(4).collect();
+                            let c = u32::from_str_radix(&code, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| format!("invalid escape \\u{}", code))?;
+                            value.push(c);
+                        }
+                        Some(c) => return Err(format!("invalid escape \\{}", c)),
+                        None => return Err(String::from("unterminated string")),
+                    },
+                    Some(c) => value.push(c),
+                    None => return Err(String::from("unterminated string")),
+                }
+            }
         }
-    }
 
-    impl Default for Schema {
-        fn default() -> Self {
-            Self::new()
+        fn array(&mut self) -> Result<Value, String> {
+            self.expect('[')?;
+            let mut items = Vec::new();
+
+            self.skip_whitespace();
+            if self.chars.next_if_eq(&']').is_some() {
+                return Ok(Value::Array(items));
+            }
+
+            loop {
+                items.push(self.value()?);
+                self.skip_whitespace();
+
+                match self.chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Value::Array(items)),
+                    _ => return Err(String::from("expected ',' or ']'")),
+                }
+            }
+        }
+
+        fn object(&mut self) -> Result<Value, String> {
+            self.expect('{')?;
+            let mut fields = Vec::new();
+
+            self.skip_whitespace();
+            if self.chars.next_if_eq(&'}').is_some() {
+                return Ok(Value::Object(fields));
+            }
+
+            loop {
+                self.skip_whitespace();
+                let key = self.string()?;
+                self.expect(':')?;
+                fields.push((key, self.value()?));
+                self.skip_whitespace();
+
+                match self.chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Value::Object(fields)),
+                    _ => return Err(String::from("expected ',' or '}'")),
+                }
+            }
         }
     }
 }
 
-/// If commands are small and may be defined as functions or passed as a closure
-/// then using this might be preferable since it does not exploit dynamic dispatch
+/// If command is a whole struct with a bunch of functions and variables defined as separated module
+/// then using this would be more suitable
 ///
 /// # Example
 /// ```
-/// # use design_patterns::patterns::command::function_pointer::FnPtr;
-/// # fn use_function_pointer(add_field: FnPtr, remove_field: FnPtr) {
-/// use design_patterns::patterns::command::function_pointer::Schema;
+/// use design_patterns::patterns::command::ddl::{ColumnDef, SqlType};
+/// use design_patterns::patterns::command::trait_object::{Schema, CreateTable, AddField};
 ///
 /// let mut schema = Schema::default();
+/// let cmd = Box::new(CreateTable::new(
+///     "users",
+///     vec![ColumnDef::new("id", SqlType::Integer)],
+/// ));
 ///
-/// schema.add_migration(
-///     || String::from("create table"),
-///     || String::from("drop table"),
-/// );
-/// schema.add_migration(add_field, remove_field);
+/// schema.add_migration(cmd);
 ///
-/// assert_eq!(vec!["create table", "add field"], schema.execute());
-/// assert_eq!(vec!["remove field", "drop table"], schema.rollback());
-/// # }
+/// let cmd = Box::new(AddField::new("users", ColumnDef::new("email", SqlType::Text)));
+///
+/// schema.add_migration(cmd);
+///
+/// assert_eq!(
+///     vec!["CREATE TABLE users (id INTEGER);", "ALTER TABLE users ADD COLUMN email TEXT;"],
+///     schema.execute()
+/// );
+/// assert_eq!(
+///     vec!["ALTER TABLE users DROP COLUMN email;", "DROP TABLE users;"],
+///     schema.rollback()
+/// );
 /// ```
-pub mod function_pointer {
-    pub type FnPtr = fn() -> String;
+pub mod trait_object {
+    use std::cell::{Cell, OnceCell};
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+    use std::fmt;
+    use std::io::{self, BufRead, Write};
+    use std::ops::Bound;
+    use std::rc::Rc;
+    use std::sync::mpsc::{self, RecvTimeoutError};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-    pub struct Command {
-        execute: FnPtr,
-        rollback: FnPtr,
+    use super::ddl::{quote_ident, ColumnDef};
+    use super::json::{self, Value};
+    use super::{MigrationError, SchemaError};
+    use crate::patterns::strategy_di::Strategy;
+
+    /// Lets boxed migrations be cloned. It is implemented for every `Migration + Clone`, so
+    /// migrations only need to derive `Clone`.
+    pub trait MigrationClone {
+        fn clone_box(&self) -> Box<dyn Migration>;
     }
 
-    pub struct Schema {
-        commands: Vec<Command>,
+    impl<T: Migration + Clone + 'static> MigrationClone for T {
+        fn clone_box(&self) -> Box<dyn Migration> {
+            Box::new(self.clone())
+        }
     }
 
-    impl Schema {
-        pub fn new() -> Self {
-            Schema {
-                commands: Vec::new(),
+    impl Clone for Box<dyn Migration> {
+        fn clone(&self) -> Self {
+            self.clone_box()
+        }
+    }
+
+    pub trait Migration: MigrationClone {
+        fn execute(&self) -> &str;
+        fn rollback(&self) -> &str;
+
+        fn name(&self) -> &str {
+            "unnamed"
+        }
+
+        /// Migrations returning `false` are skipped by `execute`/`rollback` and planned as such.
+        fn should_execute(&self) -> bool {
+            true
+        }
+
+        /// What `execute` would return, without performing it.
+        /// Migrations whose `execute` has side effects should override this.
+        fn preview(&self) -> &str {
+            self.execute()
+        }
+
+        /// What `rollback` would return, without performing it.
+        fn preview_rollback(&self) -> &str {
+            self.rollback()
+        }
+
+        /// Runs the migration in the given direction: `Up` executes, `Down` rolls back.
+        fn run(&self, direction: Direction) -> String {
+            match direction {
+                Direction::Up => self.execute().to_string(),
+                Direction::Down => self.rollback().to_string(),
             }
         }
 
-        pub fn add_migration(&mut self, execute: FnPtr, rollback: FnPtr) {
-            self.commands.push(Command { execute, rollback });
+        /// Higher priorities run first under `Schema::execute_by_priority`.
+        fn priority(&self) -> i32 {
+            0
         }
 
-        pub fn execute(&self) -> Vec<String> {
-            self.commands.iter().map(|cmd| (cmd.execute)()).collect()
+        /// Names of the migrations that have to run before this one.
+        fn depends_on(&self) -> Vec<&str> {
+            Vec::new()
         }
 
-        pub fn rollback(&self) -> Vec<String> {
-            self.commands
-                .iter()
-                .rev()
-                .map(|cmd| (cmd.rollback)())
-                .collect()
+        /// Names of the migrations `execute` touches, in order. A leaf touches only itself.
+        fn execute_trace(&self) -> Vec<&str> {
+            vec![self.name()]
         }
-    }
 
-    impl Default for Schema {
-        fn default() -> Self {
-            Self::new()
+        /// Names of the migrations `rollback` touches, in order. A leaf touches only itself.
+        fn rollback_trace(&self) -> Vec<&str> {
+            vec![self.name()]
+        }
+
+        /// Fingerprint of the migration, stored when it is applied so later edits can be detected.
+        fn checksum(&self) -> u64 {
+            checksum(self.name(), self.preview())
+        }
+
+        /// Fallible counterpart of `execute`, used by `Schema::apply`.
+        fn try_execute(&self) -> Result<String, MigrationError> {
+            Ok(self.execute().to_string())
+        }
+
+        /// Fallible counterpart of `rollback`, used by `Schema::revert`.
+        fn try_rollback(&self) -> Result<String, MigrationError> {
+            Ok(self.rollback().to_string())
+        }
+
+        /// Who wrote the migration, when and why. Empty unless the migration provides it.
+        fn metadata(&self) -> Metadata {
+            Metadata::default()
+        }
+
+        /// Whether `Schema::apply_with_state` should run this migration against `state`.
+        fn should_apply(&self, _state: &SchemaState) -> bool {
+            true
         }
+
+        /// Records the effect of `execute` in `state`. Migrations that don't create or drop
+        /// anything tracked there can leave this alone.
+        fn apply_state(&self, _state: &mut SchemaState) {}
+
+        /// Records the effect of `rollback` in `state`.
+        fn revert_state(&self, _state: &mut SchemaState) {}
     }
-}
 
-mod fn_trait_object {
-    type Migration<'a> = Box<dyn Fn() -> &'a str>;
+    /// `created_at` is in seconds since the Unix epoch, with 0 meaning unknown.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct Metadata {
+        pub author: String,
+        pub created_at: u64,
+        pub description: String,
+    }
 
-    pub struct Schema<'a> {
-        executes: Vec<Migration<'a>>,
-        rollbacks: Vec<Migration<'a>>,
+    /// The tables a schema is known to contain, and their columns, as far as the migrations
+    /// report it.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct SchemaState {
+        tables: BTreeMap<String, BTreeSet<String>>,
     }
 
-    impl<'a> Schema<'a> {
+    impl SchemaState {
         pub fn new() -> Self {
-            Schema {
-                executes: Vec::new(),
-                rollbacks: Vec::new(),
-            }
+            Self::default()
         }
 
-        pub fn add_migration<E, R>(&mut self, execute: E, rollback: R)
-        where
-            E: Fn() -> &'a str + 'static,
-            R: Fn() -> &'a str + 'static,
-        {
-            self.executes.push(Box::new(execute));
-            self.rollbacks.push(Box::new(rollback));
+        pub fn has_table(&self, name: &str) -> bool {
+            self.tables.contains_key(name)
         }
 
-        pub fn execute(&self) -> Vec<&str> {
-            self.executes.iter().map(|execute| execute()).collect()
+        pub fn create_table(&mut self, name: &str) {
+            self.tables.entry(name.to_string()).or_default();
         }
 
-        pub fn rollback(&self) -> Vec<&str> {
-            self.rollbacks
-                .iter()
-                .rev()
-                .map(|rollback| rollback())
-                .collect()
+        pub fn drop_table(&mut self, name: &str) {
+            self.tables.remove(name);
         }
-    }
 
-    impl<'a> Default for Schema<'a> {
-        fn default() -> Self {
-            Self::new()
+        pub fn tables(&self) -> impl Iterator<Item = &str> {
+            self.tables.keys().map(String::as_str)
         }
-    }
-}
 
-#[cfg(test)]
-mod test_trait_object {
-    use super::trait_object::{AddField, CreateTable, Schema};
+        pub fn has_column(&self, table: &str, column: &str) -> bool {
+            self.tables
+                .get(table)
+                .is_some_and(|columns| columns.contains(column))
+        }
 
-    #[test]
-    fn test_command() {
-        let mut schema = Schema::default();
-        let cmd = Box::new(CreateTable);
+        /// Does nothing if `table` isn't known.
+        pub fn add_column(&mut self, table: &str, column: &str) {
+            if let Some(columns) = self.tables.get_mut(table) {
+                columns.insert(column.to_string());
+            }
+        }
 
-        schema.add_migration(cmd);
+        pub fn drop_column(&mut self, table: &str, column: &str) {
+            if let Some(columns) = self.tables.get_mut(table) {
+                columns.remove(column);
+            }
+        }
 
-        let cmd = Box::new(AddField);
+        pub fn columns(&self, table: &str) -> impl Iterator<Item = &str> {
+            self.tables
+                .get(table)
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+        }
+    }
 
-        schema.add_migration(cmd);
+    /// FNV-1a over the name and a payload describing what the migration does. Unlike
+    /// `DefaultHasher` it is stable across Rust releases, so it can be persisted.
+    pub fn checksum(name: &str, payload: &str) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
 
-        assert_eq!(vec!["create table", "add field"], schema.execute());
-        assert_eq!(vec!["remove field", "drop table"], schema.rollback());
+        name.bytes()
+            .chain([0])
+            .chain(payload.bytes())
+            .fold(OFFSET_BASIS, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+            })
     }
-}
 
-#[cfg(test)]
-mod test_function_pointer {
-    use super::function_pointer::Schema;
+    /// Rolls back `completed`, newest first, after a failed run. Failed rollbacks are ignored:
+    /// the error that stopped the run is the one the caller needs to see.
+    fn roll_back_completed<'a>(completed: impl DoubleEndedIterator<Item = &'a dyn Migration>) {
+        for cmd in completed.rev() {
+            let _ = cmd.try_rollback();
+        }
+    }
 
-    fn add_field() -> String {
-        String::from("add field")
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PlanAction {
+        Execute,
+        Skip,
     }
 
-    fn remove_field() -> String {
-        String::from("remove field")
+    /// A reviewable listing of what a schema will do, and how each step is undone, before
+    /// anything is applied. Built from previews, so no migration runs.
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct MigrationPlan {
+        steps: Vec<(String, String, String)>,
+    }
+
+    impl MigrationPlan {
+        /// `(name, up, down)` for each migration, in execution order.
+        pub fn steps(&self) -> &[(String, String, String)] {
+            &self.steps
+        }
+    }
+
+    impl fmt::Display for MigrationPlan {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for (index, (name, up, down)) in self.steps.iter().enumerate() {
+                writeln!(f, "{}. {}", index + 1, name)?;
+                writeln!(f, "   up:   {}", up)?;
+                writeln!(f, "   down: {}", down)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PlanEntry<'a> {
+        pub index: usize,
+        pub name: &'a str,
+        pub action: PlanAction,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Phase {
+        Started,
+        Finished,
+    }
+
+    /// Reported around every command run by `execute_with_progress`/`rollback_with_progress`.
+    /// `index` is the command's position in the schema and `total` the schema's length.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Progress<'a> {
+        pub index: usize,
+        pub total: usize,
+        pub name: &'a str,
+        pub phase: Phase,
+    }
+
+    pub trait MigrationObserver {
+        fn before(&self, name: &str);
+        fn after(&self, name: &str, output: &str);
+
+        fn on_error(&self, _name: &str, _error: &MigrationError) {}
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Direction {
+        Up,
+        Down,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Outcome {
+        Success,
+        Failure(String),
+        /// Recorded as applied without running, see `Schema::mark_applied`.
+        Marked,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct HistoryEntry {
+        pub name: String,
+        pub checksum: Option<u64>,
+        pub timestamp: u64,
+        pub direction: Direction,
+        pub outcome: Outcome,
+    }
+
+    impl HistoryEntry {
+        fn succeeded(&self) -> bool {
+            matches!(self.outcome, Outcome::Success | Outcome::Marked)
+        }
+    }
+
+    /// Every `apply`/`revert` step a schema has taken, in the order it happened.
+    /// Migrations are identified by name, so names should be unique within a schema.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct MigrationHistory {
+        entries: Vec<HistoryEntry>,
+    }
+
+    impl MigrationHistory {
+        pub fn new() -> Self {
+            MigrationHistory {
+                entries: Vec::new(),
+            }
+        }
+
+        pub fn entries(&self) -> &[HistoryEntry] {
+            &self.entries
+        }
+
+        pub fn record(
+            &mut self,
+            name: &str,
+            checksum: Option<u64>,
+            direction: Direction,
+            outcome: Outcome,
+        ) {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs());
+
+            self.entries.push(HistoryEntry {
+                name: name.to_string(),
+                checksum,
+                timestamp,
+                direction,
+                outcome,
+            });
+        }
+
+        /// A migration is applied when its latest successful step went up.
+        pub fn is_applied(&self, name: &str) -> bool {
+            self.entries
+                .iter()
+                .rev()
+                .find(|entry| entry.name == name && entry.succeeded())
+                .is_some_and(|entry| entry.direction == Direction::Up)
+        }
+
+        /// The checksum recorded when `name` was last applied, if it is applied and one was known.
+        pub fn applied_checksum(&self, name: &str) -> Option<u64> {
+            self.entries
+                .iter()
+                .rev()
+                .find(|entry| entry.name == name && entry.succeeded())
+                .filter(|entry| entry.direction == Direction::Up)
+                .and_then(|entry| entry.checksum)
+        }
+
+        /// Names of the currently applied migrations, in the order they were applied.
+        pub fn applied(&self) -> Vec<String> {
+            let mut applied: Vec<String> = Vec::new();
+
+            for entry in self.entries.iter().filter(|entry| entry.succeeded()) {
+                applied.retain(|name| name != &entry.name);
+
+                if entry.direction == Direction::Up {
+                    applied.push(entry.name.clone());
+                }
+            }
+
+            applied
+        }
+
+        pub fn to_json(&self) -> String {
+            let entries: Vec<String> = self
+                .entries
+                .iter()
+                .map(|entry| {
+                    let direction = match entry.direction {
+                        Direction::Up => "up",
+                        Direction::Down => "down",
+                    };
+                    let (outcome, error) = match &entry.outcome {
+                        Outcome::Success => ("success", String::from("null")),
+                        Outcome::Marked => ("marked", String::from("null")),
+                        Outcome::Failure(reason) => ("failure", json::escape(reason)),
+                    };
+
+                    let checksum = entry
+                        .checksum
+                        .map_or(String::from("null"), |checksum| checksum.to_string());
+
+                    format!(
+                        "{{\"name\":{},\"checksum\":{},\"timestamp\":{},\"direction\":\"{}\",\"outcome\":\"{}\",\"error\":{}}}",
+                        json::escape(&entry.name),
+                        checksum,
+                        entry.timestamp,
+                        direction,
+                        outcome,
+                        error
+                    )
+                })
+                .collect();
+
+            format!("[{}]", entries.join(","))
+        }
+
+        pub fn from_json(input: &str) -> Result<Self, SchemaError> {
+            let invalid = |reason: &str| SchemaError::InvalidHistory {
+                reason: reason.to_string(),
+            };
+
+            let items = match json::parse(input).map_err(|reason| invalid(&reason))? {
+                Value::Array(items) => items,
+                _ => return Err(invalid("expected an array of entries")),
+            };
+
+            let entries = items
+                .iter()
+                .map(|item| {
+                    let name = match item.get("name") {
+                        Some(Value::String(name)) => name.clone(),
+                        _ => return Err(invalid("entry without a name")),
+                    };
+                    let checksum = match item.get("checksum") {
+                        Some(Value::Number(checksum)) => Some(*checksum),
+                        Some(Value::Null) | None => None,
+                        _ => return Err(invalid("entry with an invalid checksum")),
+                    };
+                    let timestamp = match item.get("timestamp") {
+                        Some(Value::Number(timestamp)) => *timestamp,
+                        _ => return Err(invalid("entry without a timestamp")),
+                    };
+                    let direction = match item.get("direction") {
+                        Some(Value::String(direction)) if direction == "up" => Direction::Up,
+                        Some(Value::String(direction)) if direction == "down" => Direction::Down,
+                        _ => return Err(invalid("entry without a valid direction")),
+                    };
+                    let outcome = match (item.get("outcome"), item.get("error")) {
+                        (Some(Value::String(outcome)), _) if outcome == "success" => {
+                            Outcome::Success
+                        }
+                        (Some(Value::String(outcome)), _) if outcome == "marked" => Outcome::Marked,
+                        (Some(Value::String(outcome)), Some(Value::String(reason)))
+                            if outcome == "failure" =>
+                        {
+                            Outcome::Failure(reason.clone())
+                        }
+                        _ => return Err(invalid("entry without a valid outcome")),
+                    };
+
+                    Ok(HistoryEntry {
+                        name,
+                        checksum,
+                        timestamp,
+                        direction,
+                        outcome,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+
+            Ok(MigrationHistory { entries })
+        }
+    }
+
+    // The SQL is rendered once up front because `Migration` hands out borrowed strings.
+    #[derive(Clone)]
+    pub struct CreateTable {
+        table: String,
+        columns: Vec<ColumnDef>,
+        migration_name: String,
+        up: String,
+        down: String,
+    }
+
+    impl CreateTable {
+        pub fn new(name: &str, columns: Vec<ColumnDef>) -> Self {
+            let table = quote_ident(name);
+            let definitions: Vec<String> = columns.iter().map(ColumnDef::to_string).collect();
+
+            CreateTable {
+                table: name.to_string(),
+                migration_name: format!("create_table:{}", name),
+                up: format!("CREATE TABLE {} ({});", table, definitions.join(", ")),
+                down: format!("DROP TABLE {};", table),
+                columns,
+            }
+        }
+
+        pub fn table(&self) -> &str {
+            &self.table
+        }
+
+        pub fn columns(&self) -> &[ColumnDef] {
+            &self.columns
+        }
+    }
+
+    impl Migration for CreateTable {
+        fn execute(&self) -> &str {
+            &self.up
+        }
+
+        fn rollback(&self) -> &str {
+            &self.down
+        }
+
+        fn name(&self) -> &str {
+            &self.migration_name
+        }
+
+        fn metadata(&self) -> Metadata {
+            Metadata {
+                author: String::from("w2u2u"),
+                created_at: 1_700_000_000,
+                description: String::from("Creates the table"),
+            }
+        }
+
+        fn apply_state(&self, state: &mut SchemaState) {
+            state.create_table(&self.table);
+
+            for column in &self.columns {
+                state.add_column(&self.table, &column.name);
+            }
+        }
+
+        fn revert_state(&self, state: &mut SchemaState) {
+            state.drop_table(&self.table);
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct AddField {
+        table: String,
+        column: ColumnDef,
+        migration_name: String,
+        up: String,
+        down: String,
+    }
+
+    impl AddField {
+        pub fn new(table: &str, column: ColumnDef) -> Self {
+            let quoted = quote_ident(table);
+
+            AddField {
+                table: table.to_string(),
+                migration_name: format!("add_field:{}.{}", table, column.name),
+                up: format!("ALTER TABLE {} ADD COLUMN {};", quoted, column),
+                down: format!(
+                    "ALTER TABLE {} DROP COLUMN {};",
+                    quoted,
+                    quote_ident(&column.name)
+                ),
+                column,
+            }
+        }
+
+        pub fn table(&self) -> &str {
+            &self.table
+        }
+
+        pub fn column(&self) -> &ColumnDef {
+            &self.column
+        }
+    }
+
+    impl Migration for AddField {
+        fn execute(&self) -> &str {
+            &self.up
+        }
+
+        fn rollback(&self) -> &str {
+            &self.down
+        }
+
+        fn name(&self) -> &str {
+            &self.migration_name
+        }
+
+        fn metadata(&self) -> Metadata {
+            Metadata {
+                author: String::from("w2u2u"),
+                created_at: 1_700_086_400,
+                description: String::from("Adds a field to the table"),
+            }
+        }
+
+        fn apply_state(&self, state: &mut SchemaState) {
+            state.add_column(&self.table, &self.column.name);
+        }
+
+        fn revert_state(&self, state: &mut SchemaState) {
+            state.drop_column(&self.table, &self.column.name);
+        }
+    }
+
+    /// Groups several migrations so a schema treats them as one: children execute in order
+    /// and roll back in reverse, and composites can be nested.
+    ///
+    /// The borrowed `execute`/`rollback`/`preview` outputs are the children's joined outputs,
+    /// computed the first time they are asked for. `try_execute`/`try_rollback` run the
+    /// children every time, and a failing child rolls back the earlier children of this
+    /// composite only.
+    #[derive(Clone)]
+    pub struct CompositeMigration {
+        name: String,
+        children: Vec<Box<dyn Migration>>,
+        executed: OnceCell<String>,
+        rolled_back: OnceCell<String>,
+        previewed: OnceCell<String>,
+    }
+
+    impl CompositeMigration {
+        pub fn new(name: &str, children: Vec<Box<dyn Migration>>) -> Self {
+            CompositeMigration {
+                name: name.to_string(),
+                children,
+                executed: OnceCell::new(),
+                rolled_back: OnceCell::new(),
+                previewed: OnceCell::new(),
+            }
+        }
+    }
+
+    impl Migration for CompositeMigration {
+        fn execute(&self) -> &str {
+            self.executed.get_or_init(|| {
+                let outputs: Vec<&str> = self.children.iter().map(|cmd| cmd.execute()).collect();
+                outputs.join("; ")
+            })
+        }
+
+        fn rollback(&self) -> &str {
+            self.rolled_back.get_or_init(|| {
+                let outputs: Vec<&str> = self
+                    .children
+                    .iter()
+                    .rev()
+                    .map(|cmd| cmd.rollback())
+                    .collect();
+                outputs.join("; ")
+            })
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn preview(&self) -> &str {
+            self.previewed.get_or_init(|| {
+                let outputs: Vec<&str> = self.children.iter().map(|cmd| cmd.preview()).collect();
+                outputs.join("; ")
+            })
+        }
+
+        fn try_execute(&self) -> Result<String, MigrationError> {
+            let mut outputs = Vec::with_capacity(self.children.len());
+
+            for (index, cmd) in self.children.iter().enumerate() {
+                match cmd.try_execute() {
+                    Ok(output) => outputs.push(output),
+                    Err(err) => {
+                        roll_back_completed(self.children[..index].iter().map(|cmd| cmd.as_ref()));
+                        return Err(err);
+                    }
+                }
+            }
+
+            Ok(outputs.join("; "))
+        }
+
+        fn execute_trace(&self) -> Vec<&str> {
+            self.children
+                .iter()
+                .flat_map(|cmd| cmd.execute_trace())
+                .collect()
+        }
+
+        fn rollback_trace(&self) -> Vec<&str> {
+            self.children
+                .iter()
+                .rev()
+                .flat_map(|cmd| cmd.rollback_trace())
+                .collect()
+        }
+
+        fn try_rollback(&self) -> Result<String, MigrationError> {
+            let outputs = self
+                .children
+                .iter()
+                .rev()
+                .map(|cmd| cmd.try_rollback())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(outputs.join("; "))
+        }
+
+        fn apply_state(&self, state: &mut SchemaState) {
+            for cmd in &self.children {
+                cmd.apply_state(state);
+            }
+        }
+
+        fn revert_state(&self, state: &mut SchemaState) {
+            for cmd in self.children.iter().rev() {
+                cmd.revert_state(state);
+            }
+        }
+    }
+
+    type Guard = Box<dyn Fn(&SchemaState) -> bool>;
+
+    /// Runs `inner` under `Schema::apply_with_state` only when `guard` accepts the current
+    /// state, e.g. to create a table only if it doesn't exist yet.
+    #[derive(Clone)]
+    pub struct GuardedMigration {
+        inner: Box<dyn Migration>,
+        guard: Rc<dyn Fn(&SchemaState) -> bool>,
+    }
+
+    impl GuardedMigration {
+        pub fn new(inner: Box<dyn Migration>, guard: Guard) -> Self {
+            GuardedMigration {
+                inner,
+                guard: Rc::from(guard),
+            }
+        }
+    }
+
+    impl Migration for GuardedMigration {
+        fn execute(&self) -> &str {
+            self.inner.execute()
+        }
+
+        fn rollback(&self) -> &str {
+            self.inner.rollback()
+        }
+
+        fn name(&self) -> &str {
+            self.inner.name()
+        }
+
+        fn should_apply(&self, state: &SchemaState) -> bool {
+            (self.guard)(state) && self.inner.should_apply(state)
+        }
+
+        fn apply_state(&self, state: &mut SchemaState) {
+            self.inner.apply_state(state);
+        }
+
+        fn revert_state(&self, state: &mut SchemaState) {
+            self.inner.revert_state(state);
+        }
+    }
+
+    /// Runs a `strategy_di::Strategy` as a migration. Executing it computes the strategy's
+    /// result on first use and outputs it as text.
+    #[derive(Clone)]
+    pub struct StrategyMigration {
+        strategy: Rc<dyn Strategy>,
+        a: i32,
+        b: i32,
+        output: OnceCell<String>,
+    }
+
+    impl StrategyMigration {
+        pub fn new(strategy: Box<dyn Strategy>, a: i32, b: i32) -> Self {
+            StrategyMigration {
+                strategy: Rc::from(strategy),
+                a,
+                b,
+                output: OnceCell::new(),
+            }
+        }
+    }
+
+    impl Migration for StrategyMigration {
+        fn execute(&self) -> &str {
+            self.output
+                .get_or_init(|| self.strategy.execute_strategy(self.a, self.b).to_string())
+        }
+
+        fn rollback(&self) -> &str {
+            "discard computed value"
+        }
+
+        fn name(&self) -> &str {
+            "strategy"
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum ApplyStatus {
+        Applied(String),
+        Skipped,
+    }
+
+    /// What `Schema::apply_with_state` did with each command, in schema order. Pass it to
+    /// `Schema::revert_with_state` to undo exactly the commands that ran.
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct ApplyReport {
+        entries: Vec<(usize, String, ApplyStatus)>,
+    }
+
+    impl ApplyReport {
+        pub fn status(&self, name: &str) -> Option<&ApplyStatus> {
+            self.entries
+                .iter()
+                .find(|(_, entry, _)| entry == name)
+                .map(|(_, _, status)| status)
+        }
+
+        pub fn entries(&self) -> impl Iterator<Item = (&str, &ApplyStatus)> {
+            self.entries
+                .iter()
+                .map(|(_, name, status)| (name.as_str(), status))
+        }
+    }
+
+    /// What a run of `Schema::execute` or `Schema::apply` did, handed to `on_complete`.
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct RunSummary {
+        pub executed: Vec<String>,
+        pub failed: Option<String>,
+    }
+
+    /// What the user chose during `Schema::run_interactive`: the commands applied, in order,
+    /// and the ones rolled back afterwards, newest first.
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct InteractiveRun {
+        pub applied: Vec<String>,
+        pub rolled_back: Vec<String>,
+    }
+
+    type NameHook = Rc<dyn Fn(&str)>;
+    type OutputHook = Rc<dyn Fn(&str, &str)>;
+    type ErrorHook = Rc<dyn Fn(&str, &MigrationError)>;
+    type CompleteHook = Rc<dyn Fn(&RunSummary)>;
+
+    /// Callbacks around `Schema::execute` and `Schema::apply`. A failing command gets
+    /// `on_error` instead of `after_each`, and `on_complete` fires once the run stops.
+    #[derive(Clone, Default)]
+    pub struct SchemaHooks {
+        before_each: Option<NameHook>,
+        after_each: Option<OutputHook>,
+        on_error: Option<ErrorHook>,
+        on_complete: Option<CompleteHook>,
+    }
+
+    impl SchemaHooks {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn before_each(mut self, hook: impl Fn(&str) + 'static) -> Self {
+            self.before_each = Some(Rc::new(hook));
+            self
+        }
+
+        pub fn after_each(mut self, hook: impl Fn(&str, &str) + 'static) -> Self {
+            self.after_each = Some(Rc::new(hook));
+            self
+        }
+
+        pub fn on_error(mut self, hook: impl Fn(&str, &MigrationError) + 'static) -> Self {
+            self.on_error = Some(Rc::new(hook));
+            self
+        }
+
+        pub fn on_complete(mut self, hook: impl Fn(&RunSummary) + 'static) -> Self {
+            self.on_complete = Some(Rc::new(hook));
+            self
+        }
+
+        fn fire_before(&self, name: &str) {
+            if let Some(hook) = &self.before_each {
+                hook(name);
+            }
+        }
+
+        fn fire_after(&self, name: &str, output: &str) {
+            if let Some(hook) = &self.after_each {
+                hook(name, output);
+            }
+        }
+
+        fn fire_error(&self, name: &str, error: &MigrationError) {
+            if let Some(hook) = &self.on_error {
+                hook(name, error);
+            }
+        }
+
+        fn fire_complete(&self, summary: &RunSummary) {
+            if let Some(hook) = &self.on_complete {
+                hook(summary);
+            }
+        }
+    }
+
+    /// How `Schema::apply_with_retry` treats `MigrationError::Transient` failures. Every
+    /// migration gets at most `max_attempts` tries, with `delay` between them.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RetryPolicy {
+        pub max_attempts: u32,
+        pub delay: Duration,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Attempt {
+        pub name: String,
+        pub attempt: u32,
+        pub result: Result<String, MigrationError>,
+    }
+
+    /// Every attempt made by `Schema::apply_with_retry`, in order, and how the run ended.
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct RetryReport {
+        attempts: Vec<Attempt>,
+        error: Option<MigrationError>,
+        rolled_back: Vec<String>,
+    }
+
+    impl RetryReport {
+        pub fn attempts(&self) -> &[Attempt] {
+            &self.attempts
+        }
+
+        /// How many times the named migration was tried.
+        pub fn attempts_for(&self, name: &str) -> u32 {
+            self.attempts
+                .iter()
+                .filter(|attempt| attempt.name == name)
+                .count() as u32
+        }
+
+        pub fn is_success(&self) -> bool {
+            self.error.is_none()
+        }
+
+        /// The error that made the run give up, if it did.
+        pub fn error(&self) -> Option<&MigrationError> {
+            self.error.as_ref()
+        }
+
+        /// Names of the migrations undone after giving up, newest first.
+        pub fn rolled_back(&self) -> &[String] {
+            &self.rolled_back
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RollbackEntry {
+        pub name: String,
+        pub result: Result<String, MigrationError>,
+    }
+
+    /// The outcome of `Schema::rollback_checked`, one entry per command attempted, newest first.
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct RollbackReport {
+        entries: Vec<RollbackEntry>,
+    }
+
+    impl RollbackReport {
+        pub fn entries(&self) -> &[RollbackEntry] {
+            &self.entries
+        }
+
+        pub fn is_success(&self) -> bool {
+            self.entries.iter().all(|entry| entry.result.is_ok())
+        }
+
+        pub fn failures(&self) -> impl Iterator<Item = &RollbackEntry> {
+            self.entries.iter().filter(|entry| entry.result.is_err())
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TransactionStatus {
+        Committed,
+        RolledBack,
+        /// At least one rollback failed, so the schema is only partly reverted.
+        RollbackFailed,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RunEntry {
+        pub name: String,
+        pub duration: Duration,
+        pub result: Result<String, MigrationError>,
+    }
+
+    /// The outcome of `Schema::apply_transactional`: every command attempted, in order, how
+    /// many were rolled back because one of them failed, and the rollbacks that failed.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RunReport {
+        pub status: TransactionStatus,
+        pub entries: Vec<RunEntry>,
+        pub rollback_steps: usize,
+        pub rollback_failures: Vec<RollbackEntry>,
+    }
+
+    impl RunReport {
+        /// The command that made the run roll back.
+        pub fn failed(&self) -> Option<&RunEntry> {
+            self.entries.iter().find(|entry| entry.result.is_err())
+        }
+    }
+
+    impl fmt::Display for RunReport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.failed() {
+                Some(failed) => writeln!(
+                    f,
+                    "rolled back after {} failed, {} rollback steps, {} failed",
+                    failed.name,
+                    self.rollback_steps,
+                    self.rollback_failures.len()
+                )?,
+                None => writeln!(f, "committed {} migrations", self.entries.len())?,
+            }
+
+            for entry in &self.entries {
+                match &entry.result {
+                    Ok(output) => writeln!(
+                        f,
+                        "  ok     {} ({:?}): {}",
+                        entry.name, entry.duration, output
+                    )?,
+                    Err(err) => {
+                        writeln!(f, "  failed {} ({:?}): {}", entry.name, entry.duration, err)?
+                    }
+                }
+            }
+
+            for failure in &self.rollback_failures {
+                if let Err(err) = &failure.result {
+                    writeln!(f, "  stuck  {}: {}", failure.name, err)?;
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    /// The outcome of `Schema::execute_until`, needed to roll back exactly those commands.
+    /// It doesn't borrow the schema, so the schema can keep being used, and even mutated,
+    /// while a checkpoint is held; any further run or change to the commands makes the
+    /// checkpoint stale.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Checkpoint {
+        outputs: Vec<String>,
+        executed: usize,
+        epoch: u64,
+    }
+
+    impl Checkpoint {
+        pub fn outputs(&self) -> &[String] {
+            &self.outputs
+        }
+
+        /// How many of the schema's leading commands were run.
+        pub fn executed(&self) -> usize {
+            self.executed
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct Schema {
+        commands: Vec<Box<dyn Migration>>,
+        history: MigrationHistory,
+        // Bumped through `next_epoch` by every method that runs or changes the commands, so
+        // checkpoints taken before can be told apart.
+        epoch: Cell<u64>,
+        observers: Vec<Rc<dyn MigrationObserver>>,
+        hooks: SchemaHooks,
+    }
+
+    impl Schema {
+        pub fn new() -> Self {
+            Schema {
+                commands: Vec::new(),
+                history: MigrationHistory::new(),
+                epoch: Cell::new(0),
+                observers: Vec::new(),
+                hooks: SchemaHooks::new(),
+            }
+        }
+
+        pub fn set_hooks(&mut self, hooks: SchemaHooks) {
+            self.hooks = hooks;
+        }
+
+        pub fn from_migrations(migrations: Vec<Box<dyn Migration>>) -> Self {
+            migrations.into_iter().collect()
+        }
+
+        /// Registers an observer notified before and after each command run by `execute`.
+        pub fn subscribe(&mut self, observer: Rc<dyn MigrationObserver>) {
+            self.observers.push(observer);
+        }
+
+        pub fn history(&self) -> &MigrationHistory {
+            &self.history
+        }
+
+        /// Replaces the recorded history, e.g. with one loaded from `MigrationHistory::from_json`,
+        /// so `apply` skips whatever it says is already applied.
+        pub fn restore_history(&mut self, history: MigrationHistory) {
+            self.history = history;
+        }
+
+        pub fn applied(&self) -> Vec<String> {
+            self.history.applied()
+        }
+
+        /// Records `name` as applied without running it, for bootstrapping against an
+        /// environment that already has some migrations in place.
+        pub fn mark_applied(&mut self, name: &str) {
+            let checksum = self
+                .commands
+                .iter()
+                .find(|cmd| cmd.name() == name)
+                .map(|cmd| cmd.checksum());
+
+            self.history
+                .record(name, checksum, Direction::Up, Outcome::Marked);
+        }
+
+        /// Executes the migrations that are not applied yet, recording each step in the history.
+        /// Stops at the first failure; the migrations applied before it stay applied.
+        ///
+        /// Already applied migrations are checked against the checksum recorded for them and
+        /// a `ChecksumMismatch` is returned, before anything runs, if one has been edited.
+        pub fn apply(&mut self) -> Result<Vec<String>, MigrationError> {
+            self.apply_checked(false)
+        }
+
+        /// Like `apply`, but re-accepts edited migrations by recording their new checksum.
+        pub fn apply_forced(&mut self) -> Result<Vec<String>, MigrationError> {
+            self.apply_checked(true)
+        }
+
+        fn apply_checked(&mut self, force: bool) -> Result<Vec<String>, MigrationError> {
+            self.next_epoch();
+
+            for cmd in &self.commands {
+                let actual = cmd.checksum();
+
+                match self.history.applied_checksum(cmd.name()) {
+                    Some(expected) if expected != actual && force => {
+                        self.history.record(
+                            cmd.name(),
+                            Some(actual),
+                            Direction::Up,
+                            Outcome::Marked,
+                        );
+                    }
+                    Some(expected) if expected != actual => {
+                        return Err(MigrationError::ChecksumMismatch {
+                            name: cmd.name().to_string(),
+                            expected,
+                            actual,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+
+            let mut outputs = Vec::new();
+            let mut summary = RunSummary::default();
+
+            for cmd in self.commands.iter().filter(|cmd| cmd.should_execute()) {
+                if self.history.is_applied(cmd.name()) {
+                    continue;
+                }
+
+                self.hooks.fire_before(cmd.name());
+
+                match cmd.try_execute() {
+                    Ok(output) => {
+                        self.history.record(
+                            cmd.name(),
+                            Some(cmd.checksum()),
+                            Direction::Up,
+                            Outcome::Success,
+                        );
+                        self.hooks.fire_after(cmd.name(), &output);
+                        summary.executed.push(cmd.name().to_string());
+                        outputs.push(output);
+                    }
+                    Err(err) => {
+                        self.history.record(
+                            cmd.name(),
+                            Some(cmd.checksum()),
+                            Direction::Up,
+                            Outcome::Failure(err.to_string()),
+                        );
+                        self.hooks.fire_error(cmd.name(), &err);
+                        summary.failed = Some(cmd.name().to_string());
+                        self.hooks.fire_complete(&summary);
+                        return Err(err);
+                    }
+                }
+            }
+
+            self.hooks.fire_complete(&summary);
+            Ok(outputs)
+        }
+
+        /// Rolls back the applied migrations in reverse, recording each step in the history.
+        pub fn revert(&mut self) -> Result<Vec<String>, MigrationError> {
+            self.next_epoch();
+            let mut outputs = Vec::new();
+
+            for cmd in self.commands.iter().rev() {
+                if !self.history.is_applied(cmd.name()) {
+                    continue;
+                }
+
+                match cmd.try_rollback() {
+                    Ok(output) => {
+                        self.history.record(
+                            cmd.name(),
+                            Some(cmd.checksum()),
+                            Direction::Down,
+                            Outcome::Success,
+                        );
+                        outputs.push(output);
+                    }
+                    Err(err) => {
+                        self.history.record(
+                            cmd.name(),
+                            Some(cmd.checksum()),
+                            Direction::Down,
+                            Outcome::Failure(err.to_string()),
+                        );
+                        return Err(err);
+                    }
+                }
+            }
+
+            Ok(outputs)
+        }
+
+        pub fn add_migration(&mut self, cmd: Box<dyn Migration>) {
+            self.next_epoch();
+            self.commands.push(cmd);
+        }
+
+        /// Concatenates `other`'s commands after these. The history and observers of `other`
+        /// are dropped.
+        pub fn merge(mut self, other: Schema) -> Schema {
+            self.append(other);
+            self
+        }
+
+        /// Moves `other`'s commands after these. The history and observers of `other` are
+        /// dropped.
+        pub fn append(&mut self, other: Schema) {
+            self.next_epoch();
+            self.commands.extend(other.commands);
+        }
+
+        pub fn insert_migration(
+            &mut self,
+            index: usize,
+            cmd: Box<dyn Migration>,
+        ) -> Result<(), SchemaError> {
+            if index > self.commands.len() {
+                return Err(SchemaError::IndexOutOfRange {
+                    index,
+                    len: self.commands.len(),
+                });
+            }
+
+            self.next_epoch();
+            self.commands.insert(index, cmd);
+            Ok(())
+        }
+
+        pub fn remove_migration(&mut self, index: usize) -> Option<Box<dyn Migration>> {
+            if index < self.commands.len() {
+                self.next_epoch();
+                Some(self.commands.remove(index))
+            } else {
+                None
+            }
+        }
+
+        pub fn swap(&mut self, i: usize, j: usize) -> Result<(), SchemaError> {
+            let len = self.commands.len();
+
+            if let Some(&index) = [i, j].iter().find(|&&index| index >= len) {
+                return Err(SchemaError::IndexOutOfRange { index, len });
+            }
+
+            self.next_epoch();
+            self.commands.swap(i, j);
+            Ok(())
+        }
+
+        pub fn len(&self) -> usize {
+            self.commands.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.commands.is_empty()
+        }
+
+        pub fn names(&self) -> Vec<&str> {
+            self.iter_names().collect()
+        }
+
+        /// Removes every command. The applied history is kept, so `clear` followed by adding
+        /// the same migrations again won't re-apply them; see `clear_all`.
+        pub fn clear(&mut self) {
+            self.next_epoch();
+            self.commands.clear();
+        }
+
+        /// Removes the commands the history doesn't show as applied.
+        pub fn clear_pending(&mut self) {
+            self.next_epoch();
+            let history = &self.history;
+            self.commands.retain(|cmd| history.is_applied(cmd.name()));
+        }
+
+        /// Removes every command and forgets the applied history.
+        pub fn clear_all(&mut self) {
+            self.next_epoch();
+            self.commands.clear();
+            self.history = MigrationHistory::new();
+        }
+
+        /// Keeps the first `len` commands. Does nothing if there are no more than `len`.
+        pub fn truncate(&mut self, len: usize) {
+            self.next_epoch();
+            self.commands.truncate(len);
+        }
+
+        pub fn iter(&self) -> Iter<'_> {
+            Iter {
+                inner: self.commands.iter(),
+            }
+        }
+
+        pub fn iter_names(&self) -> impl DoubleEndedIterator<Item = &str> {
+            self.iter().map(|cmd| cmd.name())
+        }
+
+        pub fn execute_with_progress(&self, mut on_progress: impl FnMut(Progress)) -> Vec<&str> {
+            self.next_epoch();
+            let total = self.commands.len();
+
+            self.commands
+                .iter()
+                .enumerate()
+                .filter(|(_, cmd)| cmd.should_execute())
+                .map(|(index, cmd)| {
+                    let progress = |phase| Progress {
+                        index,
+                        total,
+                        name: cmd.name(),
+                        phase,
+                    };
+
+                    on_progress(progress(Phase::Started));
+                    let output = cmd.execute();
+                    on_progress(progress(Phase::Finished));
+
+                    output
+                })
+                .collect()
+        }
+
+        pub fn rollback_with_progress(&self, mut on_progress: impl FnMut(Progress)) -> Vec<&str> {
+            self.next_epoch();
+            let total = self.commands.len();
+
+            self.commands
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, cmd)| cmd.should_execute())
+                .map(|(index, cmd)| {
+                    let progress = |phase| Progress {
+                        index,
+                        total,
+                        name: cmd.name(),
+                        phase,
+                    };
+
+                    on_progress(progress(Phase::Started));
+                    let output = cmd.rollback();
+                    on_progress(progress(Phase::Finished));
+
+                    output
+                })
+                .collect()
+        }
+
+        /// Runs the fallible path, reporting each command to `observer`.
+        /// Stops at the first failure after handing it to `on_error`.
+        pub fn execute_with_observer(
+            &self,
+            observer: &dyn MigrationObserver,
+        ) -> Result<Vec<String>, MigrationError> {
+            self.next_epoch();
+            let mut outputs = Vec::new();
+
+            for cmd in self.commands.iter().filter(|cmd| cmd.should_execute()) {
+                observer.before(cmd.name());
+
+                match cmd.try_execute() {
+                    Ok(output) => {
+                        observer.after(cmd.name(), &output);
+                        outputs.push(output);
+                    }
+                    Err(err) => {
+                        observer.on_error(cmd.name(), &err);
+                        return Err(err);
+                    }
+                }
+            }
+
+            Ok(outputs)
+        }
+
+        pub fn execute(&self) -> Vec<&str> {
+            self.next_epoch();
+
+            let mut summary = RunSummary::default();
+            let outputs = self
+                .commands
+                .iter()
+                .filter(|cmd| cmd.should_execute())
+                .map(|cmd| {
+                    self.hooks.fire_before(cmd.name());
+                    for observer in &self.observers {
+                        observer.before(cmd.name());
+                    }
+
+                    let output = cmd.execute();
+
+                    for observer in &self.observers {
+                        observer.after(cmd.name(), output);
+                    }
+                    self.hooks.fire_after(cmd.name(), output);
+                    summary.executed.push(cmd.name().to_string());
+
+                    output
+                })
+                .collect();
+
+            self.hooks.fire_complete(&summary);
+            outputs
+        }
+
+        /// Executes each command whose `should_apply` accepts `state`, updating `state` as it
+        /// goes, and reports which ran and which were skipped.
+        pub fn apply_with_state(&self, state: &mut SchemaState) -> ApplyReport {
+            self.next_epoch();
+            let mut report = ApplyReport::default();
+
+            for (index, cmd) in self.commands.iter().enumerate() {
+                if !cmd.should_execute() {
+                    continue;
+                }
+
+                let status = if cmd.should_apply(state) {
+                    let output = cmd.execute().to_string();
+                    cmd.apply_state(state);
+                    ApplyStatus::Applied(output)
+                } else {
+                    ApplyStatus::Skipped
+                };
+
+                report.entries.push((index, cmd.name().to_string(), status));
+            }
+
+            report
+        }
+
+        /// Rolls back, newest first, the commands `report` shows as applied. Skipped commands
+        /// are left alone.
+        pub fn revert_with_state(
+            &self,
+            report: &ApplyReport,
+            state: &mut SchemaState,
+        ) -> Vec<&str> {
+            self.next_epoch();
+            let mut outputs = Vec::new();
+
+            for (index, _, status) in report.entries.iter().rev() {
+                let Some(cmd) = self.commands.get(*index) else {
+                    continue;
+                };
+
+                if *status != ApplyStatus::Skipped {
+                    outputs.push(cmd.rollback());
+                    cmd.revert_state(state);
+                }
+            }
+
+            outputs
+        }
+
+        /// Executes, in insertion order, only the commands named in `names`. Names matching no
+        /// command are ignored.
+        pub fn execute_only(&self, names: &[&str]) -> Vec<String> {
+            self.next_epoch();
+
+            self.commands
+                .iter()
+                .filter(|cmd| cmd.should_execute() && names.contains(&cmd.name()))
+                .map(|cmd| cmd.execute().to_string())
+                .collect()
+        }
+
+        /// Executes only the first `index` commands, or all of them if `index` is past the end.
+        pub fn execute_until(&self, index: usize) -> Checkpoint {
+            let epoch = self.next_epoch();
+
+            let executed = index.min(self.commands.len());
+            let outputs = self.commands[..executed]
+                .iter()
+                .filter(|cmd| cmd.should_execute())
+                .map(|cmd| cmd.execute().to_string())
+                .collect();
+
+            Checkpoint {
+                outputs,
+                executed,
+                epoch,
+            }
+        }
+
+        /// Runs the fallible path as one unit against `state`: either every command applies, or
+        /// the first failure rolls back the ones already applied, newest first.
+        pub fn apply_transactional(&self, state: &mut SchemaState) -> RunReport {
+            self.next_epoch();
+            let mut entries = Vec::new();
+            let mut applied: Vec<&dyn Migration> = Vec::new();
+
+            for cmd in self.commands.iter().filter(|cmd| cmd.should_execute()) {
+                let started = Instant::now();
+                let result = cmd.try_execute();
+                let failed = result.is_err();
+
+                if !failed {
+                    cmd.apply_state(state);
+                    applied.push(cmd.as_ref());
+                }
+
+                entries.push(RunEntry {
+                    name: cmd.name().to_string(),
+                    duration: started.elapsed(),
+                    result,
+                });
+
+                if failed {
+                    let mut rollback_steps = 0;
+                    let mut rollback_failures = Vec::new();
+
+                    for cmd in applied.iter().rev() {
+                        match cmd.try_rollback() {
+                            Ok(_) => {
+                                cmd.revert_state(state);
+                                rollback_steps += 1;
+                            }
+                            Err(err) => rollback_failures.push(RollbackEntry {
+                                name: cmd.name().to_string(),
+                                result: Err(err),
+                            }),
+                        }
+                    }
+
+                    let status = if rollback_failures.is_empty() {
+                        TransactionStatus::RolledBack
+                    } else {
+                        TransactionStatus::RollbackFailed
+                    };
+
+                    return RunReport {
+                        status,
+                        entries,
+                        rollback_steps,
+                        rollback_failures,
+                    };
+                }
+            }
+
+            RunReport {
+                status: TransactionStatus::Committed,
+                entries,
+                rollback_steps: 0,
+                rollback_failures: Vec::new(),
+            }
+        }
+
+        /// Runs the fallible path, retrying transient failures as `policy` allows. A permanent
+        /// failure, or a transient one that runs out of attempts, rolls back the commands that
+        /// completed, newest first.
+        pub fn apply_with_retry(&self, policy: RetryPolicy) -> RetryReport {
+            self.next_epoch();
+            let mut report = RetryReport::default();
+            let mut completed: Vec<&dyn Migration> = Vec::new();
+
+            for cmd in self.commands.iter().filter(|cmd| cmd.should_execute()) {
+                let mut attempt = 0;
+
+                let error = loop {
+                    attempt += 1;
+                    let result = cmd.try_execute();
+                    let outcome = result.clone();
+
+                    report.attempts.push(Attempt {
+                        name: cmd.name().to_string(),
+                        attempt,
+                        result,
+                    });
+
+                    match outcome {
+                        Ok(_) => break None,
+                        Err(MigrationError::Transient { .. }) if attempt < policy.max_attempts => {
+                            thread::sleep(policy.delay);
+                        }
+                        Err(err) => break Some(err),
+                    }
+                };
+
+                let Some(error) = error else {
+                    completed.push(cmd.as_ref());
+                    continue;
+                };
+
+                roll_back_completed(completed.iter().copied());
+                report
+                    .rolled_back
+                    .extend(completed.iter().rev().map(|done| done.name().to_string()));
+                report.error = Some(error);
+
+                break;
+            }
+
+            report
+        }
+
+        /// Executes every command, writing a `name<TAB>output` line to `journal` as each one
+        /// finishes, so an interrupted run can be picked up again with `replay`.
+        pub fn execute_journaled(&self, journal: &mut impl Write) -> io::Result<Vec<&str>> {
+            self.execute_journaled_from(0, journal)
+        }
+
+        /// Like `execute_journaled`, starting at the command with index `start`.
+        pub fn execute_journaled_from(
+            &self,
+            start: usize,
+            journal: &mut impl Write,
+        ) -> io::Result<Vec<&str>> {
+            self.next_epoch();
+            let mut outputs = Vec::new();
+
+            for cmd in self.commands.iter().skip(start) {
+                if !cmd.should_execute() {
+                    continue;
+                }
+
+                let output = cmd.execute();
+                writeln!(
+                    journal,
+                    "{}\t{}",
+                    escape_journal(cmd.name()),
+                    escape_journal(output)
+                )?;
+                journal.flush()?;
+                outputs.push(output);
+            }
+
+            Ok(outputs)
+        }
+
+        /// Reads a journal written by `execute_journaled` and returns the index of the first
+        /// command it does not record, i.e. where `execute_journaled_from` should resume.
+        /// A final line without its newline was cut short mid-write and doesn't count.
+        pub fn replay(&self, mut journal: impl BufRead) -> io::Result<usize> {
+            let mut resume_at = 0;
+            let mut line = String::new();
+
+            loop {
+                line.clear();
+                if journal.read_line(&mut line)? == 0 || !line.ends_with('\n') {
+                    break;
+                }
+
+                while self
+                    .commands
+                    .get(resume_at)
+                    .is_some_and(|cmd| !cmd.should_execute())
+                {
+                    resume_at += 1;
+                }
+
+                let name = line.split('\t').next().unwrap_or_default();
+                match self.commands.get(resume_at) {
+                    Some(cmd) if escape_journal(cmd.name()) == name => resume_at += 1,
+                    _ => break,
+                }
+            }
+
+            Ok(resume_at)
+        }
+
+        /// Prints the plan to `output`, then asks before executing each command, answering `y`
+        /// to apply it, `n` to skip it or `q` to stop. Stopping early, or running out of input,
+        /// offers to roll back whatever was applied so far.
+        pub fn run_interactive(
+            &self,
+            mut input: impl BufRead,
+            output: &mut impl Write,
+        ) -> io::Result<InteractiveRun> {
+            self.next_epoch();
+            let mut run = InteractiveRun::default();
+            let mut applied = Vec::new();
+
+            writeln!(output, "plan:")?;
+            for entry in self.plan() {
+                let action = match entry.action {
+                    PlanAction::Execute => "execute",
+                    PlanAction::Skip => "skip",
+                };
+                writeln!(output, "  {}. {} ({})", entry.index + 1, entry.name, action)?;
+            }
+
+            for cmd in self.commands.iter().filter(|cmd| cmd.should_execute()) {
+                writeln!(output, "next: {}", cmd.name())?;
+
+                match prompt(&mut input, output, "apply next? [y/n/q] ", &["y", "n", "q"])? {
+                    Some("y") => {
+                        writeln!(output, "{}", cmd.execute())?;
+                        run.applied.push(cmd.name().to_string());
+                        applied.push(cmd);
+                    }
+                    Some("n") => writeln!(output, "skipped {}", cmd.name())?,
+                    _ => {
+                        if !applied.is_empty()
+                            && prompt(
+                                &mut input,
+                                output,
+                                "roll back applied commands? [y/n] ",
+                                &["y", "n"],
+                            )? == Some("y")
+                        {
+                            for cmd in applied.into_iter().rev() {
+                                writeln!(output, "{}", cmd.rollback())?;
+                                run.rolled_back.push(cmd.name().to_string());
+                            }
+                        }
+
+                        return Ok(run);
+                    }
+                }
+            }
+
+            Ok(run)
+        }
+
+        /// Rolls back, in reverse, the commands run under `checkpoint`. Fails if the schema
+        /// has been executed again since the checkpoint was taken.
+        pub fn rollback_from(&self, checkpoint: Checkpoint) -> Result<Vec<&str>, SchemaError> {
+            if checkpoint.epoch != self.epoch.get() {
+                return Err(SchemaError::StaleCheckpoint);
+            }
+
+            self.next_epoch();
+
+            Ok(self.commands[..checkpoint.executed]
+                .iter()
+                .rev()
+                .filter(|cmd| cmd.should_execute())
+                .map(|cmd| cmd.rollback())
+                .collect())
+        }
+
+        pub fn rollback(&self) -> Vec<&str> {
+            self.next_epoch();
+
+            self.commands
+                .iter()
+                .rev()
+                .filter(|cmd| cmd.should_execute())
+                .map(|cmd| cmd.rollback())
+                .collect()
+        }
+
+        /// Rolls back every command on the fallible path, carrying on past failures so as much
+        /// as possible is undone, and reports how each one went.
+        pub fn rollback_checked(&self) -> RollbackReport {
+            self.rollback_report(false)
+        }
+
+        /// Like `rollback_checked`, but stops at the first failing rollback.
+        pub fn rollback_checked_strict(&self) -> RollbackReport {
+            self.rollback_report(true)
+        }
+
+        fn rollback_report(&self, strict: bool) -> RollbackReport {
+            self.next_epoch();
+            let mut report = RollbackReport::default();
+
+            for cmd in self
+                .commands
+                .iter()
+                .rev()
+                .filter(|cmd| cmd.should_execute())
+            {
+                let result = cmd.try_rollback();
+                let failed = result.is_err();
+
+                report.entries.push(RollbackEntry {
+                    name: cmd.name().to_string(),
+                    result,
+                });
+
+                if strict && failed {
+                    break;
+                }
+            }
+
+            report
+        }
+
+        /// Runs every command in `direction`, in reverse order when going `Down`.
+        pub fn run_all(&self, direction: Direction) -> Vec<String> {
+            self.next_epoch();
+            let commands = self.commands.iter().filter(|cmd| cmd.should_execute());
+
+            match direction {
+                Direction::Up => commands.map(|cmd| cmd.run(direction)).collect(),
+                Direction::Down => commands.rev().map(|cmd| cmd.run(direction)).collect(),
+            }
+        }
+
+        /// Every command and whether it would run. For an owned, printable plan with the up and
+        /// down actions, see `plan_document`.
+        pub fn plan(&self) -> Vec<PlanEntry<'_>> {
+            self.commands
+                .iter()
+                .enumerate()
+                .map(|(index, cmd)| PlanEntry {
+                    index,
+                    name: cmd.name(),
+                    action: if cmd.should_execute() {
+                        PlanAction::Execute
+                    } else {
+                        PlanAction::Skip
+                    },
+                })
+                .collect()
+        }
+
+        /// The plan in `execute_by_priority` order: highest priority first, with ties kept in
+        /// insertion order.
+        pub fn sorted_plan(&self) -> Vec<PlanEntry<'_>> {
+            let mut plan = self.plan();
+            plan.sort_by_key(|entry| std::cmp::Reverse(self.commands[entry.index].priority()));
+            plan
+        }
+
+        pub fn execute_by_priority(&self) -> Vec<&str> {
+            self.next_epoch();
+
+            self.sorted_plan()
+                .into_iter()
+                .filter(|entry| entry.action == PlanAction::Execute)
+                .map(|entry| self.commands[entry.index].execute())
+                .collect()
+        }
+
+        /// Undoes `execute_by_priority` in exactly the reverse of its order.
+        pub fn rollback_by_priority(&self) -> Vec<&str> {
+            self.next_epoch();
+
+            self.sorted_plan()
+                .into_iter()
+                .rev()
+                .filter(|entry| entry.action == PlanAction::Execute)
+                .map(|entry| self.commands[entry.index].rollback())
+                .collect()
+        }
+
+        /// The migrations that would execute, as a `MigrationPlan` document. Unlike `plan`,
+        /// which indexes into the schema, it owns its text and can be stored or printed.
+        ///
+        /// It can't be called `plan` itself: that name already returns the `PlanEntry` list
+        /// `sorted_plan` and `run_interactive` are built on.
+        pub fn plan_document(&self) -> MigrationPlan {
+            MigrationPlan {
+                steps: self
+                    .commands
+                    .iter()
+                    .filter(|cmd| cmd.should_execute())
+                    .map(|cmd| {
+                        (
+                            cmd.name().to_string(),
+                            cmd.preview().to_string(),
+                            cmd.preview_rollback().to_string(),
+                        )
+                    })
+                    .collect(),
+            }
+        }
+
+        pub fn execute_dry_run(&self) -> Vec<&str> {
+            self.commands
+                .iter()
+                .filter(|cmd| cmd.should_execute())
+                .map(|cmd| cmd.preview())
+                .collect()
+        }
+
+        pub fn describe(&self) -> Vec<String> {
+            self.commands
+                .iter()
+                .enumerate()
+                .map(|(index, cmd)| format!("{}. {}", index + 1, cmd.name()))
+                .collect()
+        }
+
+        /// A table of every command's name and metadata, one row per command. Missing values
+        /// are shown as `-`.
+        pub fn report(&self) -> String {
+            let mut rows = vec![[
+                String::from("name"),
+                String::from("author"),
+                String::from("created_at"),
+                String::from("description"),
+            ]];
+
+            for cmd in &self.commands {
+                let metadata = cmd.metadata();
+                let or_dash = |value: String| {
+                    if value.is_empty() {
+                        String::from("-")
+                    } else {
+                        value
+                    }
+                };
+                let created_at = match metadata.created_at {
+                    0 => String::new(),
+                    seconds => seconds.to_string(),
+                };
+
+                rows.push([
+                    cmd.name().to_string(),
+                    or_dash(metadata.author),
+                    or_dash(created_at),
+                    or_dash(metadata.description),
+                ]);
+            }
+
+            let mut widths = [0; 3];
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+
+            rows.iter()
+                .map(|[name, author, created_at, description]| {
+                    format!(
+                        "{:<w0$} | {:<w1$} | {:<w2$} | {}",
+                        name,
+                        author,
+                        created_at,
+                        description,
+                        w0 = widths[0],
+                        w1 = widths[1],
+                        w2 = widths[2],
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+
+        pub fn filter_by_author(&self, author: &str) -> Vec<&dyn Migration> {
+            self.commands
+                .iter()
+                .filter(|cmd| cmd.metadata().author == author)
+                .map(|cmd| cmd.as_ref())
+                .collect()
+        }
+
+        /// Lints the schema: returns the names of the migrations whose `rollback` is empty or
+        /// just repeats `execute`, which almost always means it was left unimplemented.
+        /// Compares previews, so no migration runs.
+        pub fn validate(&self) -> Result<(), Vec<String>> {
+            let invalid: Vec<String> = self
+                .commands
+                .iter()
+                .filter(|cmd| {
+                    let rollback = cmd.preview_rollback();
+                    rollback.trim().is_empty() || rollback == cmd.preview()
+                })
+                .map(|cmd| cmd.name().to_string())
+                .collect();
+
+            if invalid.is_empty() {
+                Ok(())
+            } else {
+                Err(invalid)
+            }
+        }
+
+        /// Checks that `rollback` touches the commands `execute` touched in the opposite order,
+        /// descending into composites, by comparing their traces.
+        pub fn verify_symmetry(&self) -> bool {
+            let executed: Vec<&str> = self
+                .commands
+                .iter()
+                .filter(|cmd| cmd.should_execute())
+                .flat_map(|cmd| cmd.execute_trace())
+                .collect();
+            let rolled_back = self
+                .commands
+                .iter()
+                .rev()
+                .filter(|cmd| cmd.should_execute())
+                .flat_map(|cmd| cmd.rollback_trace());
+
+            executed.into_iter().rev().eq(rolled_back)
+        }
+
+        pub fn find(&self, name: &str) -> Option<usize> {
+            self.commands.iter().position(|cmd| cmd.name() == name)
+        }
+
+        /// Executes the migrations so that each one runs after everything it `depends_on`,
+        /// keeping insertion order wherever the dependencies leave a choice.
+        pub fn execute_ordered(&self) -> Result<Vec<&str>, SchemaError> {
+            self.next_epoch();
+
+            Ok(self
+                .dependency_order()?
+                .into_iter()
+                .map(|index| &self.commands[index])
+                .filter(|cmd| cmd.should_execute())
+                .map(|cmd| cmd.execute())
+                .collect())
+        }
+
+        /// Starts a new run, invalidating any checkpoint taken before it.
+        fn next_epoch(&self) -> u64 {
+            let epoch = self.epoch.get() + 1;
+            self.epoch.set(epoch);
+            epoch
+        }
+
+        fn dependency_order(&self) -> Result<Vec<usize>, SchemaError> {
+            #[derive(Clone, Copy, PartialEq)]
+            enum Mark {
+                Unvisited,
+                InProgress,
+                Done,
+            }
+
+            fn visit(
+                schema: &Schema,
+                index: usize,
+                marks: &mut [Mark],
+                path: &mut Vec<usize>,
+                order: &mut Vec<usize>,
+            ) -> Result<(), SchemaError> {
+                match marks[index] {
+                    Mark::Done => return Ok(()),
+                    Mark::InProgress => {
+                        let start = path.iter().position(|&i| i == index).unwrap_or(0);
+                        let members = path[start..]
+                            .iter()
+                            .map(|&i| schema.commands[i].name().to_string())
+                            .collect();
+
+                        return Err(SchemaError::Cycle { members });
+                    }
+                    Mark::Unvisited => {}
+                }
+
+                marks[index] = Mark::InProgress;
+                path.push(index);
+
+                let cmd = &schema.commands[index];
+                for dependency in cmd.depends_on() {
+                    let dep_index =
+                        schema
+                            .find(dependency)
+                            .ok_or_else(|| SchemaError::UnknownDependency {
+                                name: cmd.name().to_string(),
+                                dependency: dependency.to_string(),
+                            })?;
+
+                    visit(schema, dep_index, marks, path, order)?;
+                }
+
+                path.pop();
+                marks[index] = Mark::Done;
+                order.push(index);
+
+                Ok(())
+            }
+
+            let mut marks = vec![Mark::Unvisited; self.commands.len()];
+            let mut path = Vec::new();
+            let mut order = Vec::with_capacity(self.commands.len());
+
+            for index in 0..self.commands.len() {
+                visit(self, index, &mut marks, &mut path, &mut order)?;
+            }
+
+            Ok(order)
+        }
+    }
+
+    impl Default for Schema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// Writes `question` until a line of `input` matches one of `answers`. `None` at end of input.
+    fn prompt(
+        input: &mut impl BufRead,
+        output: &mut impl Write,
+        question: &str,
+        answers: &[&'static str],
+    ) -> io::Result<Option<&'static str>> {
+        let mut line = String::new();
+
+        loop {
+            write!(output, "{}", question)?;
+            output.flush()?;
+
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+
+            let answer = line.trim().to_ascii_lowercase();
+            if let Some(&answer) = answers.iter().find(|&&candidate| candidate == answer) {
+                return Ok(Some(answer));
+            }
+        }
+    }
+
+    fn escape_journal(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+    }
+
+    impl fmt::Debug for Schema {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let names: Vec<&str> = self.iter_names().collect();
+
+            f.debug_struct("Schema").field("commands", &names).finish()
+        }
+    }
+
+    /// Schemas are equal when they hold migrations with the same names in the same order.
+    impl PartialEq for Schema {
+        fn eq(&self, other: &Self) -> bool {
+            self.iter_names().eq(other.iter_names())
+        }
+    }
+
+    pub struct Iter<'a> {
+        inner: std::slice::Iter<'a, Box<dyn Migration>>,
+    }
+
+    impl<'a> Iterator for Iter<'a> {
+        type Item = &'a dyn Migration;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.inner.next().map(|cmd| cmd.as_ref())
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.inner.size_hint()
+        }
+    }
+
+    impl DoubleEndedIterator for Iter<'_> {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            self.inner.next_back().map(|cmd| cmd.as_ref())
+        }
+    }
+
+    impl ExactSizeIterator for Iter<'_> {}
+
+    impl<'a> IntoIterator for &'a Schema {
+        type Item = &'a dyn Migration;
+        type IntoIter = Iter<'a>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter()
+        }
+    }
+
+    impl Extend<Box<dyn Migration>> for Schema {
+        fn extend<I: IntoIterator<Item = Box<dyn Migration>>>(&mut self, iter: I) {
+            self.next_epoch();
+            self.commands.extend(iter);
+        }
+    }
+
+    impl FromIterator<Box<dyn Migration>> for Schema {
+        fn from_iter<I: IntoIterator<Item = Box<dyn Migration>>>(iter: I) -> Self {
+            let mut schema = Schema::new();
+            schema.extend(iter);
+            schema
+        }
+    }
+
+    /// Assembles a `Schema` fluently, boxing each migration on the way in.
+    pub struct SchemaBuilder {
+        commands: Vec<Box<dyn Migration>>,
+    }
+
+    impl SchemaBuilder {
+        pub fn new() -> Self {
+            SchemaBuilder {
+                commands: Vec::new(),
+            }
+        }
+
+        #[allow(clippy::should_implement_trait)]
+        pub fn add<M: Migration + 'static>(mut self, migration: M) -> Self {
+            self.commands.push(Box::new(migration));
+            self
+        }
+
+        pub fn build(self) -> Schema {
+            Schema::from_migrations(self.commands)
+        }
+    }
+
+    impl Default for SchemaBuilder {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// Migrations keyed by version. `current_version` is the newest version applied so far,
+    /// with 0 meaning none, so registered versions start at 1.
+    pub struct VersionedSchema {
+        commands: BTreeMap<u64, Box<dyn Migration>>,
+        current_version: u64,
+    }
+
+    impl VersionedSchema {
+        pub fn new() -> Self {
+            VersionedSchema {
+                commands: BTreeMap::new(),
+                current_version: 0,
+            }
+        }
+
+        pub fn add_migration(
+            &mut self,
+            version: u64,
+            cmd: Box<dyn Migration>,
+        ) -> Result<(), SchemaError> {
+            if version == 0 {
+                return Err(SchemaError::InvalidVersion { version });
+            }
+
+            if self.commands.contains_key(&version) {
+                return Err(SchemaError::DuplicateVersion { version });
+            }
+
+            self.commands.insert(version, cmd);
+            Ok(())
+        }
+
+        /// Combines both schemas, failing on the first version registered in both. The merged
+        /// schema keeps this schema's `current_version`.
+        pub fn merge(mut self, other: VersionedSchema) -> Result<VersionedSchema, SchemaError> {
+            self.append(other)?;
+            Ok(self)
+        }
+
+        /// Moves `other`'s migrations into this schema. Nothing is moved if any version is
+        /// registered in both.
+        pub fn append(&mut self, other: VersionedSchema) -> Result<(), SchemaError> {
+            if let Some(&version) = other
+                .commands
+                .keys()
+                .find(|version| self.commands.contains_key(version))
+            {
+                return Err(SchemaError::DuplicateVersion { version });
+            }
+
+            self.commands.extend(other.commands);
+            Ok(())
+        }
+
+        pub fn current_version(&self) -> u64 {
+            self.current_version
+        }
+
+        /// Removes the migrations newer than `current_version`, keeping the applied ones.
+        pub fn clear_pending(&mut self) {
+            if let Some(next) = self.current_version.checked_add(1) {
+                self.commands.split_off(&next);
+            }
+        }
+
+        /// Removes every migration and resets `current_version` to 0.
+        pub fn clear_all(&mut self) {
+            self.commands.clear();
+            self.current_version = 0;
+        }
+
+        /// Executes every migration newer than `current_version`, in version order.
+        pub fn execute(&mut self) -> Vec<&str> {
+            let mut outputs = Vec::new();
+
+            let pending = (Bound::Excluded(self.current_version), Bound::Unbounded);
+
+            for (&version, cmd) in self.commands.range(pending) {
+                outputs.push(cmd.execute());
+                self.current_version = version;
+            }
+
+            outputs
+        }
+
+        /// Rolls back, newest first, every applied migration above `version`.
+        pub fn rollback_to(&mut self, version: u64) -> Vec<&str> {
+            let mut outputs = Vec::new();
+
+            if version >= self.current_version {
+                return outputs;
+            }
+
+            for (_, cmd) in self
+                .commands
+                .range((
+                    Bound::Excluded(version),
+                    Bound::Included(self.current_version),
+                ))
+                .rev()
+            {
+                outputs.push(cmd.rollback());
+            }
+            self.current_version = version;
+
+            outputs
+        }
+    }
+
+    impl Default for VersionedSchema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// Wraps a `Schema` so each migration runs at most once, keyed by `Migration::name`.
+    /// Migrations seen before report `already applied: {name}` instead of executing again.
+    pub struct IdempotentSchema {
+        schema: Schema,
+        applied: HashSet<String>,
+    }
+
+    impl IdempotentSchema {
+        pub fn new() -> Self {
+            IdempotentSchema {
+                schema: Schema::new(),
+                applied: HashSet::new(),
+            }
+        }
+
+        pub fn add_migration(&mut self, cmd: Box<dyn Migration>) {
+            self.schema.add_migration(cmd);
+        }
+
+        pub fn is_applied(&self, name: &str) -> bool {
+            self.applied.contains(name)
+        }
+
+        pub fn execute(&mut self) -> Vec<String> {
+            let mut outputs = Vec::new();
+
+            for cmd in self.schema.iter().filter(|cmd| cmd.should_execute()) {
+                if self.applied.insert(cmd.name().to_string()) {
+                    outputs.push(cmd.execute().to_string());
+                } else {
+                    outputs.push(format!("already applied: {}", cmd.name()));
+                }
+            }
+
+            outputs
+        }
+    }
+
+    impl Default for IdempotentSchema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// A schema that can be moved to, or shared between, threads. It only accepts migrations
+    /// that are themselves `Send + Sync`, which plain structs like `CreateTable` already are.
+    pub struct SyncSchema {
+        commands: Vec<Arc<dyn Migration + Send + Sync>>,
+    }
+
+    impl SyncSchema {
+        pub fn new() -> Self {
+            SyncSchema {
+                commands: Vec::new(),
+            }
+        }
+
+        pub fn add_migration(&mut self, cmd: Box<dyn Migration + Send + Sync>) {
+            self.commands.push(Arc::from(cmd));
+        }
+
+        pub fn len(&self) -> usize {
+            self.commands.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.commands.is_empty()
+        }
+
+        pub fn execute(&self) -> Vec<&str> {
+            self.commands
+                .iter()
+                .filter(|cmd| cmd.should_execute())
+                .map(|cmd| cmd.execute())
+                .collect()
+        }
+
+        pub fn rollback(&self) -> Vec<&str> {
+            self.commands
+                .iter()
+                .rev()
+                .filter(|cmd| cmd.should_execute())
+                .map(|cmd| cmd.rollback())
+                .collect()
+        }
+
+        /// Runs the fallible path with each command on a worker thread, waiting at most
+        /// `per_command` for it (`Duration::ZERO` means no limit). On a timeout or failure the
+        /// commands that completed are rolled back in reverse and the error is returned.
+        ///
+        /// Threads can't be cancelled, so a command that times out keeps running on its worker
+        /// after this returns, and whatever it still does is not rolled back. This lives here
+        /// rather than on `Schema` because only `Send` commands can be handed to a worker.
+        pub fn execute_with_timeout(
+            &self,
+            per_command: Duration,
+        ) -> Result<Vec<String>, MigrationError> {
+            let mut completed = Vec::new();
+            let mut outputs = Vec::new();
+
+            for cmd in self.commands.iter().filter(|cmd| cmd.should_execute()) {
+                let (sender, receiver) = mpsc::channel();
+                let worker = Arc::clone(cmd);
+                thread::spawn(move || {
+                    // Nobody is listening any more if the command overran.
+                    let _ = sender.send(worker.try_execute());
+                });
+
+                let received = if per_command.is_zero() {
+                    receiver.recv().map_err(RecvTimeoutError::from)
+                } else {
+                    receiver.recv_timeout(per_command)
+                };
+
+                let error = match received {
+                    Ok(Ok(output)) => {
+                        completed.push(cmd);
+                        outputs.push(output);
+                        continue;
+                    }
+                    Ok(Err(err)) => err,
+                    Err(RecvTimeoutError::Timeout) => MigrationError::TimedOut {
+                        name: cmd.name().to_string(),
+                    },
+                    Err(RecvTimeoutError::Disconnected) => MigrationError::Failed {
+                        name: cmd.name().to_string(),
+                        reason: String::from("worker thread panicked"),
+                    },
+                };
+
+                roll_back_completed(completed.iter().map(|cmd| cmd.as_ref() as &dyn Migration));
+
+                return Err(error);
+            }
+
+            Ok(outputs)
+        }
+    }
+
+    impl Default for SyncSchema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// Values shared by the commands of one `CtxSchema::apply_with_ctx` run, so earlier commands
+    /// can hand data to later ones.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct ExecutionContext {
+        values: HashMap<String, String>,
+    }
+
+    impl ExecutionContext {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn insert(&mut self, key: &str, value: &str) {
+            self.values.insert(key.to_string(), value.to_string());
+        }
+
+        pub fn get(&self, key: &str) -> Option<&str> {
+            self.values.get(key).map(String::as_str)
+        }
+    }
+
+    pub trait CtxMigration {
+        fn execute(&self, ctx: &mut ExecutionContext) -> Result<String, MigrationError>;
+
+        fn name(&self) -> &str {
+            "unnamed"
+        }
+    }
+
+    pub struct CtxSchema {
+        commands: Vec<Box<dyn CtxMigration>>,
+    }
+
+    impl CtxSchema {
+        pub fn new() -> Self {
+            CtxSchema {
+                commands: Vec::new(),
+            }
+        }
+
+        pub fn add_migration(&mut self, cmd: Box<dyn CtxMigration>) {
+            self.commands.push(cmd);
+        }
+
+        /// Runs every command against one fresh context, stopping at the first error.
+        pub fn apply_with_ctx(&self) -> Result<(Vec<String>, ExecutionContext), MigrationError> {
+            let mut ctx = ExecutionContext::new();
+            let outputs = self
+                .commands
+                .iter()
+                .map(|cmd| cmd.execute(&mut ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok((outputs, ctx))
+        }
+    }
+
+    impl Default for CtxSchema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// Migrations stored under unique keys, kept in insertion order, so a single one can be
+    /// targeted for a re-run.
+    pub struct KeyedSchema {
+        commands: Vec<(String, Box<dyn Migration>)>,
+    }
+
+    impl KeyedSchema {
+        pub fn new() -> Self {
+            KeyedSchema {
+                commands: Vec::new(),
+            }
+        }
+
+        pub fn add(&mut self, key: &str, cmd: Box<dyn Migration>) -> Result<(), SchemaError> {
+            if self.position(key).is_some() {
+                return Err(SchemaError::DuplicateKey {
+                    key: key.to_string(),
+                });
+            }
+
+            self.commands.push((key.to_string(), cmd));
+            Ok(())
+        }
+
+        /// Combines both schemas, `other`'s migrations last, failing on the first key
+        /// registered in both.
+        pub fn merge(mut self, other: KeyedSchema) -> Result<KeyedSchema, SchemaError> {
+            self.append(other)?;
+            Ok(self)
+        }
+
+        /// Moves `other`'s migrations after these. Nothing is moved if any key is registered
+        /// in both.
+        pub fn append(&mut self, other: KeyedSchema) -> Result<(), SchemaError> {
+            if let Some(key) = other.keys().find(|key| self.position(key).is_some()) {
+                return Err(SchemaError::DuplicateKey {
+                    key: key.to_string(),
+                });
+            }
+
+            self.commands.extend(other.commands);
+            Ok(())
+        }
+
+        pub fn remove(&mut self, key: &str) -> Option<Box<dyn Migration>> {
+            let index = self.position(key)?;
+            Some(self.commands.remove(index).1)
+        }
+
+        pub fn keys(&self) -> impl Iterator<Item = &str> {
+            self.commands.iter().map(|(key, _)| key.as_str())
+        }
+
+        /// Executes the migration stored under `key`. `None` if there isn't one or it is
+        /// disabled through `should_execute`.
+        pub fn execute_one(&self, key: &str) -> Option<String> {
+            let index = self.position(key)?;
+            let cmd = &self.commands[index].1;
+            cmd.should_execute().then(|| cmd.execute().to_string())
+        }
+
+        pub fn execute_all(&self) -> Vec<&str> {
+            self.commands
+                .iter()
+                .filter(|(_, cmd)| cmd.should_execute())
+                .map(|(_, cmd)| cmd.execute())
+                .collect()
+        }
+
+        fn position(&self, key: &str) -> Option<usize> {
+            self.commands
+                .iter()
+                .position(|(existing, _)| existing == key)
+        }
+    }
+
+    impl Default for KeyedSchema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// How `SnapshotSchema::apply` undoes a run that fails partway.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum RollbackStrategy {
+        /// Rolls back each applied command, newest first, trusting every `revert_state`.
+        PerCommand,
+        /// Restores a copy of the state taken before the run (a memento). No command is rolled
+        /// back, so a missing or buggy `revert_state` can't leave the state half undone.
+        Snapshot,
+    }
+
+    pub struct SnapshotSchema {
+        commands: Vec<Box<dyn Migration>>,
+    }
+
+    impl SnapshotSchema {
+        pub fn new() -> Self {
+            SnapshotSchema {
+                commands: Vec::new(),
+            }
+        }
+
+        pub fn add_migration(&mut self, cmd: Box<dyn Migration>) {
+            self.commands.push(cmd);
+        }
+
+        /// Executes every command against `state`. On the first failure `state` is put back
+        /// the way it was using `strategy` and the error is returned. Under `PerCommand` a
+        /// failing rollback stops there and its error is returned instead, since `state` is
+        /// then only partly reverted.
+        pub fn apply(
+            &self,
+            state: &mut SchemaState,
+            strategy: RollbackStrategy,
+        ) -> Result<Vec<String>, MigrationError> {
+            let snapshot = match strategy {
+                RollbackStrategy::Snapshot => Some(state.clone()),
+                RollbackStrategy::PerCommand => None,
+            };
+            let mut applied = Vec::new();
+            let mut outputs = Vec::new();
+
+            for cmd in self.commands.iter().filter(|cmd| cmd.should_execute()) {
+                match cmd.try_execute() {
+                    Ok(output) => {
+                        cmd.apply_state(state);
+                        applied.push(cmd);
+                        outputs.push(output);
+                    }
+                    Err(err) => {
+                        match snapshot {
+                            Some(snapshot) => *state = snapshot,
+                            None => {
+                                for cmd in applied.into_iter().rev() {
+                                    cmd.try_rollback()?;
+                                    cmd.revert_state(state);
+                                }
+                            }
+                        }
+
+                        return Err(err);
+                    }
+                }
+            }
+
+            Ok(outputs)
+        }
+    }
+
+    impl Default for SnapshotSchema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+/// Deferred execution: migrations are queued now and run later, all at once or in stages
+///
+/// # Example
+/// ```
+/// use design_patterns::patterns::command::ddl::{ColumnDef, SqlType};
+/// use design_patterns::patterns::command::queue::CommandQueue;
+/// use design_patterns::patterns::command::trait_object::{AddField, CreateTable};
+///
+/// let mut queue = CommandQueue::new();
+///
+/// queue.enqueue(Box::new(CreateTable::new(
+///     "users",
+///     vec![ColumnDef::new("id", SqlType::Integer)],
+/// )));
+/// queue.enqueue(Box::new(AddField::new(
+///     "users",
+///     ColumnDef::new("email", SqlType::Text),
+/// )));
+///
+/// assert_eq!(
+///     Some(String::from("CREATE TABLE users (id INTEGER);")),
+///     queue.tick()
+/// );
+/// assert_eq!(
+///     vec!["ALTER TABLE users ADD COLUMN email TEXT;"],
+///     queue.drain_execute()
+/// );
+/// assert_eq!(None, queue.tick());
+/// ```
+pub mod queue {
+    use std::collections::VecDeque;
+
+    use super::trait_object::Migration;
+
+    /// Commands held back until the caller drains them, first in first out, either in bulk or
+    /// one `tick` at a time. Drained commands are kept so they can still be rolled back, until
+    /// `clear_history` forgets them.
+    pub struct CommandQueue {
+        pending: VecDeque<Box<dyn Migration>>,
+        executed: Vec<Box<dyn Migration>>,
+    }
+
+    impl CommandQueue {
+        pub fn new() -> Self {
+            CommandQueue {
+                pending: VecDeque::new(),
+                executed: Vec::new(),
+            }
+        }
+
+        pub fn enqueue(&mut self, cmd: Box<dyn Migration>) {
+            self.pending.push_back(cmd);
+        }
+
+        pub fn len(&self) -> usize {
+            self.pending.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.pending.is_empty()
+        }
+
+        pub fn drain_execute(&mut self) -> Vec<String> {
+            self.drain_n(self.pending.len())
+        }
+
+        /// Executes and removes the first `n` queued commands, or all of them if fewer are
+        /// queued.
+        pub fn drain_n(&mut self, n: usize) -> Vec<String> {
+            let count = n.min(self.pending.len());
+            let mut outputs = Vec::new();
+
+            for cmd in self.pending.drain(..count) {
+                if cmd.should_execute() {
+                    outputs.push(cmd.execute().to_string());
+                    self.executed.push(cmd);
+                }
+            }
+
+            outputs
+        }
+
+        /// Rolls back every drained command, newest first. Each is only rolled back once.
+        pub fn rollback(&mut self) -> Vec<String> {
+            self.executed
+                .drain(..)
+                .rev()
+                .map(|cmd| cmd.rollback().to_string())
+                .collect()
+        }
+
+        /// Executes the next queued command, one per call, or returns `None` once the queue is
+        /// empty. Commands that shouldn't execute are dropped without using up the tick.
+        pub fn tick(&mut self) -> Option<String> {
+            while let Some(cmd) = self.pending.pop_front() {
+                if cmd.should_execute() {
+                    let output = cmd.execute().to_string();
+                    self.executed.push(cmd);
+                    return Some(output);
+                }
+            }
+
+            None
+        }
+
+        pub fn clear_history(&mut self) {
+            self.executed.clear();
+        }
+    }
+
+    impl Default for CommandQueue {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+/// Column definitions and identifier quoting for the DDL that `trait_object::CreateTable` and
+/// `AddField` render. Identifiers are quoted only when they need it: reserved words and
+/// anything that isn't a plain `[A-Za-z_][A-Za-z0-9_]*`
+///
+/// # Example
+/// ```
+/// use design_patterns::patterns::command::ddl::{ColumnDef, SqlType};
+/// use design_patterns::patterns::command::trait_object::{CreateTable, Migration};
+///
+/// let users = CreateTable::new(
+///     "users",
+///     vec![
+///         ColumnDef::new("id", SqlType::Integer),
+///         ColumnDef::new("email", SqlType::Text),
+///     ],
+/// );
+///
+/// assert_eq!("CREATE TABLE users (id INTEGER, email TEXT);", users.execute());
+/// assert_eq!("DROP TABLE users;", users.rollback());
+/// ```
+pub mod ddl {
+    use std::fmt;
+
+    const RESERVED: [&str; 30] = [
+        "ADD",
+        "ALTER",
+        "AND",
+        "AS",
+        "BY",
+        "CHECK",
+        "COLUMN",
+        "CREATE",
+        "DEFAULT",
+        "DELETE",
+        "DROP",
+        "FROM",
+        "GROUP",
+        "INDEX",
+        "INSERT",
+        "JOIN",
+        "KEY",
+        "NOT",
+        "NULL",
+        "ON",
+        "OR",
+        "ORDER",
+        "PRIMARY",
+        "REFERENCES",
+        "SELECT",
+        "TABLE",
+        "UNIQUE",
+        "UPDATE",
+        "USER",
+        "WHERE",
+    ];
+
+    /// `name` as-is when it's a plain, unreserved identifier, otherwise double-quoted with any
+    /// embedded quotes doubled.
+    pub fn quote_ident(name: &str) -> String {
+        let mut chars = name.chars();
+        let plain = chars
+            .next()
+            .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        let reserved = RESERVED.iter().any(|word| word.eq_ignore_ascii_case(name));
+
+        if plain && !reserved {
+            name.to_string()
+        } else {
+            format!("\"{}\"", name.replace('"', "\"\""))
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SqlType {
+        Integer,
+        Real,
+        Text,
+        Boolean,
+        Blob,
+    }
+
+    impl fmt::Display for SqlType {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let name = match self {
+                SqlType::Integer => "INTEGER",
+                SqlType::Real => "REAL",
+                SqlType::Text => "TEXT",
+                SqlType::Boolean => "BOOLEAN",
+                SqlType::Blob => "BLOB",
+            };
+
+            write!(f, "{}", name)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ColumnDef {
+        pub name: String,
+        pub sql_type: SqlType,
+    }
+
+    impl ColumnDef {
+        pub fn new(name: &str, sql_type: SqlType) -> Self {
+            ColumnDef {
+                name: name.to_string(),
+                sql_type,
+            }
+        }
+    }
+
+    impl fmt::Display for ColumnDef {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{} {}", quote_ident(&self.name), self.sql_type)
+        }
+    }
+}
+
+/// If commands are small and may be defined as functions or passed as a closure
+/// then using this might be preferable since it does not exploit dynamic dispatch
+///
+/// # Example
+/// ```
+/// # use design_patterns::patterns::command::function_pointer::FnPtr;
+/// # fn use_function_pointer(add_field: FnPtr, remove_field: FnPtr) {
+/// use design_patterns::patterns::command::function_pointer::Schema;
+///
+/// let mut schema = Schema::default();
+///
+/// schema.add_migration(
+///     || String::from("create table"),
+///     || String::from("drop table"),
+/// );
+/// schema.add_migration(add_field, remove_field);
+///
+/// assert_eq!(vec!["create table", "add field"], schema.execute());
+/// assert_eq!(vec!["remove field", "drop table"], schema.rollback());
+/// # }
+/// ```
+pub mod function_pointer {
+    use super::SchemaError;
+
+    pub type FnPtr = fn() -> String;
+
+    pub struct Command {
+        execute: FnPtr,
+        rollback: FnPtr,
+    }
+
+    pub struct Schema {
+        commands: Vec<Command>,
+    }
+
+    impl Schema {
+        pub fn new() -> Self {
+            Schema {
+                commands: Vec::new(),
+            }
+        }
+
+        pub fn add_migration(&mut self, execute: FnPtr, rollback: FnPtr) {
+            self.commands.push(Command { execute, rollback });
+        }
+
+        pub fn merge(mut self, other: Schema) -> Schema {
+            self.append(other);
+            self
+        }
+
+        pub fn append(&mut self, other: Schema) {
+            self.commands.extend(other.commands);
+        }
+
+        pub fn insert_migration(
+            &mut self,
+            index: usize,
+            execute: FnPtr,
+            rollback: FnPtr,
+        ) -> Result<(), SchemaError> {
+            if index > self.commands.len() {
+                return Err(SchemaError::IndexOutOfRange {
+                    index,
+                    len: self.commands.len(),
+                });
+            }
+
+            self.commands.insert(index, Command { execute, rollback });
+            Ok(())
+        }
+
+        pub fn remove_migration(&mut self, index: usize) -> Option<(FnPtr, FnPtr)> {
+            if index < self.commands.len() {
+                let cmd = self.commands.remove(index);
+                Some((cmd.execute, cmd.rollback))
+            } else {
+                None
+            }
+        }
+
+        pub fn swap(&mut self, i: usize, j: usize) -> Result<(), SchemaError> {
+            let len = self.commands.len();
+
+            if let Some(&index) = [i, j].iter().find(|&&index| index >= len) {
+                return Err(SchemaError::IndexOutOfRange { index, len });
+            }
+
+            self.commands.swap(i, j);
+            Ok(())
+        }
+
+        pub fn len(&self) -> usize {
+            self.commands.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.commands.is_empty()
+        }
+
+        pub fn clear(&mut self) {
+            self.commands.clear();
+        }
+
+        /// Keeps the first `len` commands. Does nothing if there are no more than `len`.
+        pub fn truncate(&mut self, len: usize) {
+            self.commands.truncate(len);
+        }
+
+        pub fn iter(&self) -> impl DoubleEndedIterator<Item = (FnPtr, FnPtr)> + '_ {
+            self.commands.iter().map(|cmd| (cmd.execute, cmd.rollback))
+        }
+
+        pub fn execute(&self) -> Vec<String> {
+            self.commands.iter().map(|cmd| (cmd.execute)()).collect()
+        }
+
+        pub fn rollback(&self) -> Vec<String> {
+            self.commands
+                .iter()
+                .rev()
+                .map(|cmd| (cmd.rollback)())
+                .collect()
+        }
+    }
+
+    impl Default for Schema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl Extend<(FnPtr, FnPtr)> for Schema {
+        fn extend<I: IntoIterator<Item = (FnPtr, FnPtr)>>(&mut self, iter: I) {
+            self.commands.extend(
+                iter.into_iter()
+                    .map(|(execute, rollback)| Command { execute, rollback }),
+            );
+        }
+    }
+
+    impl FromIterator<(FnPtr, FnPtr)> for Schema {
+        fn from_iter<I: IntoIterator<Item = (FnPtr, FnPtr)>>(iter: I) -> Self {
+            let mut schema = Schema::new();
+            schema.extend(iter);
+            schema
+        }
+    }
+
+    pub type FallibleFnPtr = fn() -> Result<String, String>;
+
+    /// Why `FallibleSchema::execute` stopped, with the outputs of the commands that had
+    /// already run, in order.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PartialRun {
+        pub error: String,
+        pub completed: Vec<String>,
+    }
+
+    /// Plain function pointers that can fail. Execution stops at the first error.
+    pub struct FallibleSchema {
+        commands: Vec<(FallibleFnPtr, FallibleFnPtr)>,
+    }
+
+    impl FallibleSchema {
+        pub fn new() -> Self {
+            FallibleSchema {
+                commands: Vec::new(),
+            }
+        }
+
+        pub fn add_migration(&mut self, execute: FallibleFnPtr, rollback: FallibleFnPtr) {
+            self.commands.push((execute, rollback));
+        }
+
+        pub fn execute(&self) -> Result<Vec<String>, PartialRun> {
+            Self::run(self.commands.iter().map(|(execute, _)| execute))
+        }
+
+        pub fn rollback(&self) -> Result<Vec<String>, PartialRun> {
+            Self::run(self.commands.iter().rev().map(|(_, rollback)| rollback))
+        }
+
+        fn run<'a>(
+            commands: impl Iterator<Item = &'a FallibleFnPtr>,
+        ) -> Result<Vec<String>, PartialRun> {
+            let mut completed = Vec::new();
+
+            for cmd in commands {
+                match cmd() {
+                    Ok(output) => completed.push(output),
+                    Err(error) => return Err(PartialRun { error, completed }),
+                }
+            }
+
+            Ok(completed)
+        }
+    }
+
+    impl Default for FallibleSchema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+/// If commands are closures that only hand back text known up front, boxing `Fn` closures
+/// accepts both closures and functions while keeping the borrowed outputs of the trait objects
+///
+/// # Example
+/// ```
+/// use design_patterns::patterns::command::fn_trait_object::Schema;
+///
+/// let mut schema = Schema::default();
+///
+/// schema.add_migration(|| "create table", || "drop table");
+/// schema.add_migration(|| "add field", || "remove field");
+///
+/// assert_eq!(vec!["create table", "add field"], schema.execute());
+/// assert_eq!(vec!["remove field", "drop table"], schema.rollback());
+/// ```
+pub mod fn_trait_object {
+    type Migration<'a> = Box<dyn Fn() -> &'a str>;
+
+    pub struct Schema<'a> {
+        executes: Vec<Migration<'a>>,
+        rollbacks: Vec<Migration<'a>>,
+    }
+
+    impl<'a> Schema<'a> {
+        pub fn new() -> Self {
+            Schema {
+                executes: Vec::new(),
+                rollbacks: Vec::new(),
+            }
+        }
+
+        pub fn add_migration<E, R>(&mut self, execute: E, rollback: R)
+        where
+            E: Fn() -> &'a str + 'static,
+            R: Fn() -> &'a str + 'static,
+        {
+            self.executes.push(Box::new(execute));
+            self.rollbacks.push(Box::new(rollback));
+        }
+
+        pub fn execute(&self) -> Vec<&str> {
+            self.executes.iter().map(|execute| execute()).collect()
+        }
+
+        pub fn rollback(&self) -> Vec<&str> {
+            self.rollbacks
+                .iter()
+                .rev()
+                .map(|rollback| rollback())
+                .collect()
+        }
+    }
+
+    impl<'a> Default for Schema<'a> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+/// If commands compute their text when they run (timestamps, generated names, ...) then boxed
+/// `Fn` closures returning an owned `String` drop the borrowed lifetime of `fn_trait_object`
+///
+/// # Example
+/// ```
+/// use design_patterns::patterns::command::fn_owned::Schema;
+///
+/// let table = String::from("users");
+///
+/// let mut schema = Schema::default();
+///
+/// schema.add_migration(
+///     move || format!("create table {table}"),
+///     || String::from("drop table"),
+/// );
+///
+/// assert_eq!(vec!["create table users"], schema.execute());
+/// assert_eq!(vec!["drop table"], schema.rollback());
+/// ```
+pub mod fn_owned {
+    type Migration = Box<dyn Fn() -> String>;
+
+    pub struct Schema {
+        executes: Vec<Migration>,
+        rollbacks: Vec<Migration>,
+    }
+
+    impl Schema {
+        pub fn new() -> Self {
+            Schema {
+                executes: Vec::new(),
+                rollbacks: Vec::new(),
+            }
+        }
+
+        pub fn add_migration<E, R>(&mut self, execute: E, rollback: R)
+        where
+            E: Fn() -> String + 'static,
+            R: Fn() -> String + 'static,
+        {
+            self.executes.push(Box::new(execute));
+            self.rollbacks.push(Box::new(rollback));
+        }
+
+        pub fn execute(&self) -> Vec<String> {
+            self.executes.iter().map(|execute| execute()).collect()
+        }
+
+        pub fn rollback(&self) -> Vec<String> {
+            self.rollbacks
+                .iter()
+                .rev()
+                .map(|rollback| rollback())
+                .collect()
+        }
+    }
+
+    impl Default for Schema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+/// If commands need to own or mutate what they capture (counters, moved buffers, ...)
+/// then boxing `FnMut` closures lifts the restrictions of plain function pointers,
+/// at the cost of dynamic dispatch and `&mut self` on execute/rollback
+///
+/// # Example
+/// ```
+/// use design_patterns::patterns::command::fn_mut::Schema;
+///
+/// let table = String::from("users");
+/// let mut runs = 0;
+///
+/// let mut schema = Schema::default();
+///
+/// schema.add_migration(
+///     move || {
+///         runs += 1;
+///         format!("create table {table} (run {runs})")
+///     },
+///     || String::from("drop table"),
+/// );
+///
+/// assert_eq!(vec!["create table users (run 1)"], schema.execute());
+/// assert_eq!(vec!["create table users (run 2)"], schema.execute());
+/// assert_eq!(vec!["drop table"], schema.rollback());
+/// ```
+pub mod fn_mut {
+    type Migration = Box<dyn FnMut() -> String>;
+
+    pub struct Schema {
+        executes: Vec<Migration>,
+        rollbacks: Vec<Migration>,
+    }
+
+    impl Schema {
+        pub fn new() -> Self {
+            Schema {
+                executes: Vec::new(),
+                rollbacks: Vec::new(),
+            }
+        }
+
+        pub fn add_migration<E, R>(&mut self, execute: E, rollback: R)
+        where
+            E: FnMut() -> String + 'static,
+            R: FnMut() -> String + 'static,
+        {
+            self.executes.push(Box::new(execute));
+            self.rollbacks.push(Box::new(rollback));
+        }
+
+        pub fn execute(&mut self) -> Vec<String> {
+            self.executes.iter_mut().map(|execute| execute()).collect()
+        }
+
+        pub fn rollback(&mut self) -> Vec<String> {
+            self.rollbacks
+                .iter_mut()
+                .rev()
+                .map(|rollback| rollback())
+                .collect()
+        }
+    }
+
+    impl Default for Schema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+/// If commands should report something richer than text (row counts, timings, ...)
+/// then making the schema generic over the output type keeps that structure intact
+///
+/// # Example
+/// ```
+/// use design_patterns::patterns::command::generic::{Migration, Schema};
+///
+/// struct InsertRows(u32);
+///
+/// impl Migration<u32> for InsertRows {
+///     fn execute(&self) -> u32 {
+///         self.0
+///     }
+///
+///     fn rollback(&self) -> u32 {
+///         self.0
+///     }
+/// }
+///
+/// let mut schema = Schema::default();
+/// schema.add_migration(Box::new(InsertRows(3)));
+/// schema.add_migration(Box::new(InsertRows(4)));
+///
+/// assert_eq!(7, schema.execute().iter().sum::<u32>());
+/// ```
+pub mod generic {
+    use std::time::Duration;
+
+    pub trait Migration<T> {
+        fn execute(&self) -> T;
+        fn rollback(&self) -> T;
+    }
+
+    /// An example of a structured output a migration could report.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct MigrationOutcome {
+        pub duration: Duration,
+        pub affected_rows: u64,
+    }
+
+    pub struct Schema<T> {
+        commands: Vec<Box<dyn Migration<T>>>,
+    }
+
+    pub type StringSchema = Schema<String>;
+
+    impl<T> Schema<T> {
+        pub fn new() -> Self {
+            Schema {
+                commands: Vec::new(),
+            }
+        }
+
+        pub fn add_migration(&mut self, cmd: Box<dyn Migration<T>>) {
+            self.commands.push(cmd);
+        }
+
+        pub fn execute(&self) -> Vec<T> {
+            self.commands.iter().map(|cmd| cmd.execute()).collect()
+        }
+
+        pub fn rollback(&self) -> Vec<T> {
+            self.commands
+                .iter()
+                .rev()
+                .map(|cmd| cmd.rollback())
+                .collect()
+        }
+    }
+
+    impl<T> Default for Schema<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+/// If a command has to give away what it captured (e.g. seed rows moved into the closure)
+/// then it can only run once, which `FnOnce` expresses. Executing consumes the schema,
+/// so running it twice is rejected at compile time
+///
+/// # Example
+/// ```compile_fail
+/// use design_patterns::patterns::command::fn_once::OneShotSchema;
+///
+/// let schema = OneShotSchema::new().migration(|| String::from("seed"), || String::from("unseed"));
+///
+/// schema.execute();
+/// schema.execute(); // use of moved value
+/// ```
+pub mod fn_once {
+    type Migration = Box<dyn FnOnce() -> String>;
+
+    pub struct OneShotSchema {
+        executes: Vec<Migration>,
+        rollbacks: Vec<Migration>,
+    }
+
+    impl OneShotSchema {
+        pub fn new() -> Self {
+            OneShotSchema {
+                executes: Vec::new(),
+                rollbacks: Vec::new(),
+            }
+        }
+
+        pub fn migration<E, R>(mut self, execute: E, rollback: R) -> Self
+        where
+            E: FnOnce() -> String + 'static,
+            R: FnOnce() -> String + 'static,
+        {
+            self.executes.push(Box::new(execute));
+            self.rollbacks.push(Box::new(rollback));
+            self
+        }
+
+        pub fn execute(self) -> Vec<String> {
+            self.executes.into_iter().map(|execute| execute()).collect()
+        }
+
+        pub fn rollback(self) -> Vec<String> {
+            self.rollbacks
+                .into_iter()
+                .rev()
+                .map(|rollback| rollback())
+                .collect()
+        }
+    }
+
+    impl Default for OneShotSchema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+/// Real migrations usually wait on the network, so commands can return futures instead.
+/// The sequential path awaits them one after another, while independent commands may run
+/// concurrently with an upper bound on how many are in flight at once
+///
+/// # Example
+/// ```
+/// use design_patterns::patterns::command::async_schema::{AsyncMigration, AsyncSchema};
+/// use futures::future::{self, LocalBoxFuture};
+/// use futures::FutureExt;
+///
+/// struct CreateTable;
+///
+/// impl AsyncMigration for CreateTable {
+///     fn execute(&self) -> LocalBoxFuture<'_, String> {
+///         future::ready(String::from("create table")).boxed_local()
+///     }
+///
+///     fn rollback(&self) -> LocalBoxFuture<'_, String> {
+///         future::ready(String::from("drop table")).boxed_local()
+///     }
+/// }
+///
+/// let mut schema = AsyncSchema::default();
+/// schema.add_migration(Box::new(CreateTable));
+///
+/// assert_eq!(vec!["create table"], futures::executor::block_on(schema.execute()));
+/// assert_eq!(vec!["drop table"], futures::executor::block_on(schema.rollback()));
+/// ```
+pub mod async_schema {
+    use futures::future::LocalBoxFuture;
+    use futures::stream::{self, StreamExt};
+
+    pub trait AsyncMigration {
+        fn execute(&self) -> LocalBoxFuture<'_, String>;
+        fn rollback(&self) -> LocalBoxFuture<'_, String>;
+    }
+
+    pub struct AsyncSchema {
+        commands: Vec<Box<dyn AsyncMigration>>,
+    }
+
+    impl AsyncSchema {
+        pub fn new() -> Self {
+            AsyncSchema {
+                commands: Vec::new(),
+            }
+        }
+
+        pub fn add_migration(&mut self, cmd: Box<dyn AsyncMigration>) {
+            self.commands.push(cmd);
+        }
+
+        pub async fn execute(&self) -> Vec<String> {
+            let mut outputs = Vec::with_capacity(self.commands.len());
+
+            for cmd in &self.commands {
+                outputs.push(cmd.execute().await);
+            }
+
+            outputs
+        }
+
+        pub async fn rollback(&self) -> Vec<String> {
+            let mut outputs = Vec::with_capacity(self.commands.len());
+
+            for cmd in self.commands.iter().rev() {
+                outputs.push(cmd.rollback().await);
+            }
+
+            outputs
+        }
+
+        /// Runs up to `max_in_flight` commands at the same time, so it is only suitable for
+        /// commands that do not depend on each other. Outputs come back in completion order.
+        pub async fn execute_concurrent(&self, max_in_flight: usize) -> Vec<String> {
+            stream::iter(self.commands.iter().map(|cmd| cmd.execute()))
+                .buffer_unordered(max_in_flight.max(1))
+                .collect()
+                .await
+        }
+    }
+
+    impl Default for AsyncSchema {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+/// The same idea outside migrations: edits to a text buffer as commands that know how to undo
+/// themselves, with a history to step back and forth through them. Positions and lengths
+/// count `char`s, so edits never split a multibyte character
+///
+/// # Example
+/// ```
+/// use design_patterns::patterns::command::editor::{Delete, Editor, Insert};
+///
+/// let mut editor = Editor::new("hello");
+///
+/// editor.execute(Box::new(Insert::new(5, " world")));
+/// editor.execute(Box::new(Delete::new(0, 6)));
+/// assert_eq!("world", editor.text());
+///
+/// editor.undo();
+/// assert_eq!("hello world", editor.text());
+/// ```
+pub mod editor {
+    use std::cell::{Cell, RefCell};
+
+    pub trait Command {
+        fn apply(&self, buffer: &mut String);
+        fn unapply(&self, buffer: &mut String);
+    }
+
+    // Byte offset of the `pos`-th char, clamped to the end of the buffer.
+    fn byte_offset(buffer: &str, pos: usize) -> usize {
+        buffer
+            .char_indices()
+            .nth(pos)
+            .map_or(buffer.len(), |(offset, _)| offset)
+    }
+
+    /// Remembers where `apply` actually inserted, as a position past the end is clamped, so
+    /// `unapply` removes exactly that text.
+    pub struct Insert {
+        pos: usize,
+        text: String,
+        inserted_at: Cell<usize>,
+    }
+
+    impl Insert {
+        pub fn new(pos: usize, text: &str) -> Self {
+            Insert {
+                pos,
+                text: text.to_string(),
+                inserted_at: Cell::new(0),
+            }
+        }
+    }
+
+    impl Command for Insert {
+        fn apply(&self, buffer: &mut String) {
+            let start = byte_offset(buffer, self.pos);
+
+            buffer.insert_str(start, &self.text);
+            self.inserted_at.set(start);
+        }
+
+        fn unapply(&self, buffer: &mut String) {
+            let start = self.inserted_at.get();
+            buffer.replace_range(start..start + self.text.len(), "");
+        }
+    }
+
+    /// Remembers the text it removed on `apply` so `unapply` can put it back.
+    pub struct Delete {
+        pos: usize,
+        len: usize,
+        removed: RefCell<String>,
+    }
+
+    impl Delete {
+        pub fn new(pos: usize, len: usize) -> Self {
+            Delete {
+                pos,
+                len,
+                removed: RefCell::new(String::new()),
+            }
+        }
+    }
+
+    impl Command for Delete {
+        fn apply(&self, buffer: &mut String) {
+            let start = byte_offset(buffer, self.pos);
+            let end = byte_offset(buffer, self.pos + self.len);
+
+            *self.removed.borrow_mut() = buffer.drain(start..end).collect();
+        }
+
+        fn unapply(&self, buffer: &mut String) {
+            buffer.insert_str(byte_offset(buffer, self.pos), &self.removed.borrow());
+        }
+    }
+
+    /// Applied commands, newest last, and the undone ones waiting to be redone.
+    pub struct EditorHistory {
+        done: Vec<Box<dyn Command>>,
+        undone: Vec<Box<dyn Command>>,
+    }
+
+    impl EditorHistory {
+        pub fn new() -> Self {
+            EditorHistory {
+                done: Vec::new(),
+                undone: Vec::new(),
+            }
+        }
+
+        /// Applies `command` and records it. Anything undone can no longer be redone.
+        pub fn execute(&mut self, command: Box<dyn Command>, buffer: &mut String) {
+            command.apply(buffer);
+            self.done.push(command);
+            self.undone.clear();
+        }
+
+        /// Reverts the newest command, returning `false` if there was nothing to undo.
+        pub fn undo(&mut self, buffer: &mut String) -> bool {
+            let Some(command) = self.done.pop() else {
+                return false;
+            };
+
+            command.unapply(buffer);
+            self.undone.push(command);
+            true
+        }
+
+        /// Re-applies the newest undone command, returning `false` if there was nothing to redo.
+        pub fn redo(&mut self, buffer: &mut String) -> bool {
+            let Some(command) = self.undone.pop() else {
+                return false;
+            };
+
+            command.apply(buffer);
+            self.done.push(command);
+            true
+        }
+    }
+
+    impl Default for EditorHistory {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    pub struct Editor {
+        buffer: String,
+        history: EditorHistory,
+    }
+
+    impl Editor {
+        pub fn new(text: &str) -> Self {
+            Editor {
+                buffer: text.to_string(),
+                history: EditorHistory::new(),
+            }
+        }
+
+        pub fn text(&self) -> &str {
+            &self.buffer
+        }
+
+        pub fn execute(&mut self, command: Box<dyn Command>) {
+            self.history.execute(command, &mut self.buffer);
+        }
+
+        pub fn undo(&mut self) -> bool {
+            self.history.undo(&mut self.buffer)
+        }
+
+        pub fn redo(&mut self) -> bool {
+            self.history.redo(&mut self.buffer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_trait_object {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::ddl::{ColumnDef, SqlType};
+    use super::trait_object::{
+        checksum, AddField, ApplyStatus, CompositeMigration, CreateTable, CtxMigration, CtxSchema,
+        Direction, ExecutionContext, GuardedMigration, IdempotentSchema, KeyedSchema, Metadata,
+        Migration, MigrationHistory, MigrationObserver, Outcome, Phase, PlanAction, PlanEntry,
+        Progress, RetryPolicy, RollbackEntry, RollbackStrategy, RunSummary, Schema, SchemaBuilder,
+        SchemaHooks, SchemaState, SnapshotSchema, StrategyMigration, SyncSchema, TransactionStatus,
+        VersionedSchema,
+    };
+    use super::{MigrationError, SchemaError};
+
+    fn create_table() -> CreateTable {
+        CreateTable::new("users", vec![ColumnDef::new("id", SqlType::Integer)])
+    }
+
+    fn add_field() -> AddField {
+        AddField::new("users", ColumnDef::new("email", SqlType::Text))
+    }
+
+    #[derive(Clone)]
+    struct Counter {
+        runs: Rc<Cell<u32>>,
+    }
+
+    impl Migration for Counter {
+        fn execute(&self) -> &str {
+            self.runs.set(self.runs.get() + 1);
+            "increment"
+        }
+
+        fn rollback(&self) -> &str {
+            self.runs.set(self.runs.get() - 1);
+            "decrement"
+        }
+
+        fn name(&self) -> &str {
+            "counter"
+        }
+
+        fn preview(&self) -> &str {
+            "increment"
+        }
+
+        fn preview_rollback(&self) -> &str {
+            "decrement"
+        }
+    }
+
+    #[derive(Clone)]
+    struct Step {
+        name: &'static str,
+        deps: Vec<&'static str>,
+    }
+
+    impl Step {
+        fn new(name: &'static str, deps: &[&'static str]) -> Box<Self> {
+            Box::new(Step {
+                name,
+                deps: deps.to_vec(),
+            })
+        }
+    }
+
+    impl Migration for Step {
+        fn execute(&self) -> &str {
+            self.name
+        }
+
+        fn rollback(&self) -> &str {
+            self.name
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn depends_on(&self) -> Vec<&str> {
+            self.deps.clone()
+        }
+    }
+
+    #[derive(Clone)]
+    struct Flaky {
+        name: &'static str,
+        runs: Rc<Cell<u32>>,
+        fail: bool,
+    }
+
+    impl Flaky {
+        fn new(name: &'static str, runs: &Rc<Cell<u32>>, fail: bool) -> Box<Self> {
+            Box::new(Flaky {
+                name,
+                runs: Rc::clone(runs),
+                fail,
+            })
+        }
+    }
+
+    impl Migration for Flaky {
+        fn execute(&self) -> &str {
+            self.name
+        }
+
+        fn rollback(&self) -> &str {
+            self.name
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn try_execute(&self) -> Result<String, MigrationError> {
+            self.runs.set(self.runs.get() + 1);
+
+            if self.fail {
+                Err(MigrationError::Failed {
+                    name: self.name.to_string(),
+                    reason: String::from("boom"),
+                })
+            } else {
+                Ok(self.name.to_string())
+            }
+        }
+    }
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    #[derive(Clone)]
+    struct Logged {
+        name: &'static str,
+        log: Log,
+        fail: bool,
+    }
+
+    impl Logged {
+        fn new(name: &'static str, log: &Log, fail: bool) -> Box<Self> {
+            Box::new(Logged {
+                name,
+                log: Rc::clone(log),
+                fail,
+            })
+        }
+    }
+
+    impl Migration for Logged {
+        fn execute(&self) -> &str {
+            self.name
+        }
+
+        fn rollback(&self) -> &str {
+            self.name
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn try_execute(&self) -> Result<String, MigrationError> {
+            if self.fail {
+                return Err(MigrationError::Failed {
+                    name: self.name.to_string(),
+                    reason: String::from("boom"),
+                });
+            }
+
+            self.log.borrow_mut().push(format!("up {}", self.name));
+            Ok(format!("up {}", self.name))
+        }
+
+        fn try_rollback(&self) -> Result<String, MigrationError> {
+            self.log.borrow_mut().push(format!("down {}", self.name));
+            Ok(format!("down {}", self.name))
+        }
+    }
+
+    #[derive(Clone)]
+    struct Disabled;
+
+    impl Migration for Disabled {
+        fn execute(&self) -> &str {
+            "disabled"
+        }
+
+        fn rollback(&self) -> &str {
+            "enabled"
+        }
+
+        fn should_execute(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_command() {
+        let mut schema = Schema::default();
+        let cmd = Box::new(create_table());
+
+        schema.add_migration(cmd);
+
+        let cmd = Box::new(add_field());
+
+        schema.add_migration(cmd);
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ],
+            schema.execute()
+        );
+        assert_eq!(
+            vec!["ALTER TABLE users DROP COLUMN email;", "DROP TABLE users;"],
+            schema.rollback()
+        );
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut schema = Schema::default();
+
+        assert!(schema.plan().is_empty());
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(Disabled));
+
+        assert_eq!(
+            schema.plan(),
+            vec![
+                PlanEntry {
+                    index: 0,
+                    name: "create_table:users",
+                    action: PlanAction::Execute,
+                },
+                PlanEntry {
+                    index: 1,
+                    name: "unnamed",
+                    action: PlanAction::Skip,
+                },
+            ]
+        );
+        assert_eq!(vec!["CREATE TABLE users (id INTEGER);"], schema.execute());
+    }
+
+    #[test]
+    fn test_execute_dry_run() {
+        let mut schema = Schema::default();
+        let runs = Rc::new(Cell::new(0));
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(Counter {
+            runs: Rc::clone(&runs),
+        }));
+
+        assert_eq!(
+            vec!["CREATE TABLE users (id INTEGER);", "increment"],
+            schema.execute_dry_run()
+        );
+        assert_eq!(runs.get(), 0);
+
+        assert_eq!(
+            vec!["CREATE TABLE users (id INTEGER);", "increment"],
+            schema.execute()
+        );
+        assert_eq!(runs.get(), 1);
+    }
+
+    #[test]
+    fn test_default_name() {
+        assert_eq!(Disabled.name(), "unnamed");
+        assert_eq!(create_table().name(), "create_table:users");
+    }
+
+    #[test]
+    fn test_describe() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+
+        assert_eq!(
+            vec!["1. create_table:users", "2. add_field:users.email"],
+            schema.describe()
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+
+        assert_eq!(schema.find("add_field:users.email"), Some(1));
+        assert_eq!(schema.find("drop_everything"), None);
+    }
+
+    #[test]
+    fn test_edit_migrations() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+        schema.add_migration(Box::new(Disabled));
+
+        let removed = schema.remove_migration(1).unwrap();
+        assert_eq!(removed.name(), "add_field:users.email");
+        assert_eq!(schema.len(), 2);
+
+        schema.insert_migration(0, removed).unwrap();
+        assert_eq!(
+            vec![
+                "ALTER TABLE users ADD COLUMN email TEXT;",
+                "CREATE TABLE users (id INTEGER);"
+            ],
+            schema.execute()
+        );
+
+        schema.swap(0, 1).unwrap();
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ],
+            schema.execute()
+        );
+    }
+
+    #[test]
+    fn test_edit_migrations_out_of_range() {
+        let mut schema = Schema::default();
+
+        assert!(schema.is_empty());
+        assert!(schema.remove_migration(0).is_none());
+        assert_eq!(
+            schema.insert_migration(1, Box::new(create_table())),
+            Err(SchemaError::IndexOutOfRange { index: 1, len: 0 })
+        );
+
+        schema.add_migration(Box::new(create_table()));
+
+        assert_eq!(
+            schema.swap(0, 3),
+            Err(SchemaError::IndexOutOfRange { index: 3, len: 1 })
+        );
+    }
+
+    #[test]
+    fn test_execute_ordered() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Step::new("add_index", &["add_field:users.email"]));
+        schema.add_migration(Step::new("add_field:users.email", &["create_table:users"]));
+        schema.add_migration(Step::new("create_table:users", &[]));
+
+        assert_eq!(
+            Ok(vec![
+                "create_table:users",
+                "add_field:users.email",
+                "add_index"
+            ]),
+            schema.execute_ordered()
+        );
+    }
+
+    #[test]
+    fn test_execute_ordered_diamond() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Step::new("report", &["users", "orders"]));
+        schema.add_migration(Step::new("users", &["base"]));
+        schema.add_migration(Step::new("orders", &["base"]));
+        schema.add_migration(Step::new("base", &[]));
+
+        assert_eq!(
+            Ok(vec!["base", "users", "orders", "report"]),
+            schema.execute_ordered()
+        );
+    }
+
+    #[test]
+    fn test_execute_ordered_cycle() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Step::new("a", &["b"]));
+        schema.add_migration(Step::new("b", &["a"]));
+
+        assert_eq!(
+            Err(SchemaError::Cycle {
+                members: vec![String::from("a"), String::from("b")]
+            }),
+            schema.execute_ordered()
+        );
+
+        let mut schema = Schema::default();
+        schema.add_migration(Step::new("a", &["missing"]));
+
+        assert_eq!(
+            Err(SchemaError::UnknownDependency {
+                name: String::from("a"),
+                dependency: String::from("missing"),
+            }),
+            schema.execute_ordered()
+        );
+    }
+
+    #[test]
+    fn test_versioned_schema() {
+        let mut schema = VersionedSchema::default();
+
+        schema.add_migration(2, Step::new("two", &[])).unwrap();
+        schema.add_migration(1, Step::new("one", &[])).unwrap();
+        schema.add_migration(3, Step::new("three", &[])).unwrap();
+
+        assert_eq!(vec!["one", "two", "three"], schema.execute());
+        assert_eq!(schema.current_version(), 3);
+
+        assert!(schema.execute().is_empty());
+        assert_eq!(schema.current_version(), 3);
+
+        assert_eq!(vec!["three", "two"], schema.rollback_to(1));
+        assert_eq!(schema.current_version(), 1);
+
+        assert_eq!(vec!["two", "three"], schema.execute());
+        assert_eq!(schema.current_version(), 3);
+    }
+
+    #[test]
+    fn test_versioned_schema_rejects_versions() {
+        let mut schema = VersionedSchema::default();
+
+        schema.add_migration(1, Box::new(create_table())).unwrap();
+
+        assert_eq!(
+            schema.add_migration(1, Box::new(add_field())),
+            Err(SchemaError::DuplicateVersion { version: 1 })
+        );
+        assert_eq!(
+            schema.add_migration(0, Box::new(add_field())),
+            Err(SchemaError::InvalidVersion { version: 0 })
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let tables = SchemaBuilder::new()
+            .add(create_table())
+            .add(*Step::new("create posts", &[]))
+            .build();
+        let fields = SchemaBuilder::new()
+            .add(add_field())
+            .add(*Step::new("add title", &[]))
+            .build();
+
+        let schema = tables.merge(fields);
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "create posts",
+                "ALTER TABLE users ADD COLUMN email TEXT;",
+                "add title"
+            ],
+            schema.execute()
+        );
+        assert_eq!(
+            vec![
+                "add title",
+                "ALTER TABLE users DROP COLUMN email;",
+                "create posts",
+                "DROP TABLE users;"
+            ],
+            schema.rollback()
+        );
+
+        let mut schema = SchemaBuilder::new().add(create_table()).build();
+        schema.append(SchemaBuilder::new().add(add_field()).build());
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ],
+            schema.execute()
+        );
+    }
+
+    #[test]
+    fn test_merge_versioned_schemas() {
+        let mut first = VersionedSchema::default();
+        first.add_migration(1, Box::new(create_table())).unwrap();
+
+        let mut second = VersionedSchema::default();
+        second.add_migration(2, Box::new(add_field())).unwrap();
+
+        let mut merged = first.merge(second).unwrap();
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ],
+            merged.execute()
+        );
+
+        let mut clashing = VersionedSchema::default();
+        clashing.add_migration(2, Box::new(create_table())).unwrap();
+        clashing.add_migration(3, Box::new(add_field())).unwrap();
+
+        assert_eq!(
+            Err(SchemaError::DuplicateVersion { version: 2 }),
+            merged.append(clashing)
+        );
+        assert_eq!(
+            vec!["ALTER TABLE users DROP COLUMN email;", "DROP TABLE users;"],
+            merged.rollback_to(0)
+        );
+    }
+
+    #[test]
+    fn test_apply_records_history() {
+        let runs = Rc::new(Cell::new(0));
+        let mut schema = Schema::default();
+
+        schema.add_migration(Flaky::new("create_table:users", &runs, false));
+        schema.add_migration(Flaky::new("add_field:users.email", &runs, true));
+
+        assert_eq!(
+            schema.apply(),
+            Err(MigrationError::Failed {
+                name: String::from("add_field:users.email"),
+                reason: String::from("boom"),
+            })
+        );
+
+        let steps: Vec<_> = schema
+            .history()
+            .entries()
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.direction, entry.outcome.clone()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("create_table:users", Direction::Up, Outcome::Success),
+                (
+                    "add_field:users.email",
+                    Direction::Up,
+                    Outcome::Failure(String::from("migration add_field:users.email failed: boom"))
+                ),
+            ]
+        );
+
+        assert_eq!(
+            Ok(vec![String::from("create_table:users")]),
+            schema.revert()
+        );
+        assert!(!schema.history().is_applied("create_table:users"));
+    }
+
+    #[test]
+    fn test_history_json_round_trip() {
+        let runs = Rc::new(Cell::new(0));
+        let mut schema = Schema::default();
+
+        schema.add_migration(Flaky::new("create_table:users", &runs, false));
+        schema.add_migration(Flaky::new("add \"quoted\" field", &runs, false));
+
+        assert_eq!(schema.apply().unwrap().len(), 2);
+        assert_eq!(runs.get(), 2);
+
+        let json = schema.history().to_json();
+        let restored = MigrationHistory::from_json(&json).unwrap();
+        assert_eq!(&restored, schema.history());
+
+        let mut fresh = Schema::default();
+        fresh.add_migration(Flaky::new("create_table:users", &runs, false));
+        fresh.add_migration(Flaky::new("add \"quoted\" field", &runs, false));
+        fresh.restore_history(restored);
+
+        assert_eq!(Ok(Vec::new()), fresh.apply());
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[test]
+    fn test_history_from_invalid_json() {
+        assert!(MigrationHistory::from_json("").is_err());
+        assert!(MigrationHistory::from_json("[{\"name\":\"a\"}]").is_err());
+        assert_eq!(
+            MigrationHistory::from_json(" [ ] "),
+            Ok(MigrationHistory::new())
+        );
+    }
+
+    #[test]
+    fn test_composite_migration() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(CompositeMigration::new(
+            "setup",
+            vec![Box::new(create_table()), Box::new(add_field())],
+        )));
+        schema.add_migration(Step::new("add_index", &[]));
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);; ALTER TABLE users ADD COLUMN email TEXT;",
+                "add_index"
+            ],
+            schema.execute()
+        );
+        assert_eq!(
+            vec![
+                "add_index",
+                "ALTER TABLE users DROP COLUMN email;; DROP TABLE users;"
+            ],
+            schema.rollback()
+        );
+        assert_eq!(vec!["1. setup", "2. add_index"], schema.describe());
+    }
+
+    #[test]
+    fn test_nested_composite_migration() {
+        let log = Log::default();
+        let inner = CompositeMigration::new(
+            "inner",
+            vec![Logged::new("a", &log, false), Logged::new("b", &log, false)],
+        );
+        let outer = CompositeMigration::new(
+            "outer",
+            vec![Box::new(inner), Logged::new("c", &log, false)],
+        );
+
+        assert_eq!(Ok(String::from("up a; up b; up c")), outer.try_execute());
+        assert_eq!(
+            Ok(String::from("down c; down b; down a")),
+            outer.try_rollback()
+        );
+        assert_eq!(outer.execute(), "a; b; c");
+    }
+
+    #[test]
+    fn test_composite_migration_failure() {
+        let log = Log::default();
+        let mut schema = Schema::default();
+
+        schema.add_migration(Logged::new("before", &log, false));
+        schema.add_migration(Box::new(CompositeMigration::new(
+            "group",
+            vec![
+                Logged::new("a", &log, false),
+                Logged::new("b", &log, false),
+                Logged::new("broken", &log, true),
+            ],
+        )));
+
+        assert!(schema.apply().is_err());
+        assert_eq!(
+            *log.borrow(),
+            vec!["up before", "up a", "up b", "down b", "down a"]
+        );
+        assert!(schema.history().is_applied("before"));
+        assert!(!schema.history().is_applied("group"));
+    }
+
+    #[test]
+    fn test_iterate_schema() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+
+        let names: Vec<&str> = (&schema).into_iter().map(|cmd| cmd.name()).collect();
+        assert_eq!(names, vec!["create_table:users", "add_field:users.email"]);
+        assert_eq!(
+            schema.iter_names().rev().collect::<Vec<_>>(),
+            vec!["add_field:users.email", "create_table:users"]
+        );
+
+        let mut previews = Vec::new();
+        for cmd in &schema {
+            previews.push(cmd.preview());
+        }
+        assert_eq!(
+            previews,
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_schema() {
+        let migrations: Vec<Box<dyn Migration>> =
+            vec![Box::new(create_table()), Box::new(add_field())];
+        let mut schema: Schema = migrations.into_iter().collect();
+
+        schema.extend([Step::new("add_index", &[]) as Box<dyn Migration>]);
+
+        assert_eq!(schema.len(), 3);
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;",
+                "add_index"
+            ],
+            schema.execute()
+        );
+    }
+
+    #[test]
+    fn test_execute_with_progress() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+        schema.add_migration(Step::new("add_index", &[]));
+
+        let mut events = Vec::new();
+        let outputs = schema.execute_with_progress(|progress| {
+            events.push((
+                progress.index,
+                progress.total,
+                progress.name.to_string(),
+                progress.phase,
+            ))
+        });
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;",
+                "add_index"
+            ],
+            outputs
+        );
+        assert_eq!(
+            events,
+            vec![
+                (0, 3, String::from("create_table:users"), Phase::Started),
+                (0, 3, String::from("create_table:users"), Phase::Finished),
+                (1, 3, String::from("add_field:users.email"), Phase::Started),
+                (1, 3, String::from("add_field:users.email"), Phase::Finished),
+                (2, 3, String::from("add_index"), Phase::Started),
+                (2, 3, String::from("add_index"), Phase::Finished),
+            ]
+        );
+
+        let mut indices = Vec::new();
+        schema.rollback_with_progress(|Progress { index, phase, .. }| {
+            if phase == Phase::Started {
+                indices.push(index)
+            }
+        });
+
+        assert_eq!(indices, vec![2, 1, 0]);
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: RefCell<Vec<String>>,
+    }
+
+    impl MigrationObserver for RecordingObserver {
+        fn before(&self, name: &str) {
+            self.events.borrow_mut().push(format!("before {}", name));
+        }
+
+        fn after(&self, name: &str, output: &str) {
+            self.events
+                .borrow_mut()
+                .push(format!("after {}: {}", name, output));
+        }
+
+        fn on_error(&self, name: &str, error: &MigrationError) {
+            self.events
+                .borrow_mut()
+                .push(format!("error {}: {}", name, error));
+        }
+    }
+
+    #[test]
+    fn test_schema_builder() {
+        let schema = SchemaBuilder::new()
+            .add(create_table())
+            .add(add_field())
+            .build();
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ],
+            schema.execute()
+        );
+        assert_eq!(
+            schema,
+            Schema::from_migrations(vec![Box::new(create_table()), Box::new(add_field())])
+        );
+        assert!(SchemaBuilder::default().build().is_empty());
+    }
+
+    #[test]
+    fn test_execute_only() {
+        let schema = SchemaBuilder::new()
+            .add(create_table())
+            .add(*Step::new("create posts", &[]))
+            .add(add_field())
+            .build();
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ],
+            schema.execute_only(&["add_field:users.email", "missing", "create_table:users"])
+        );
+        assert!(schema.execute_only(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let observer = Rc::new(RecordingObserver::default());
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+        schema.subscribe(observer.clone());
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ],
+            schema.execute()
+        );
+        assert_eq!(
+            *observer.events.borrow(),
+            vec![
+                "before create_table:users",
+                "after create_table:users: CREATE TABLE users (id INTEGER);",
+                "before add_field:users.email",
+                "after add_field:users.email: ALTER TABLE users ADD COLUMN email TEXT;",
+            ]
+        );
+
+        schema.rollback();
+        assert_eq!(observer.events.borrow().len(), 4);
+    }
+
+    #[test]
+    fn test_execute_with_observer() {
+        let log = Log::default();
+        let observer = RecordingObserver::default();
+        let mut schema = Schema::default();
+
+        schema.add_migration(Logged::new("a", &log, false));
+        schema.add_migration(Logged::new("b", &log, true));
+        schema.add_migration(Logged::new("c", &log, false));
+
+        assert!(schema.execute_with_observer(&observer).is_err());
+        assert_eq!(
+            *observer.events.borrow(),
+            vec![
+                "before a",
+                "after a: up a",
+                "before b",
+                "error b: migration b failed: boom",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_execute_until_checkpoint() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+        schema.add_migration(Step::new("add_index", &[]));
+
+        let checkpoint = schema.execute_until(2);
+
+        assert_eq!(
+            checkpoint.outputs(),
+            [
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ]
+        );
+        assert_eq!(checkpoint.executed(), 2);
+        assert_eq!(
+            Ok(vec![
+                "ALTER TABLE users DROP COLUMN email;",
+                "DROP TABLE users;"
+            ]),
+            schema.rollback_from(checkpoint)
+        );
+    }
+
+    #[test]
+    fn test_execute_until_past_end() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+
+        let checkpoint = schema.execute_until(10);
+
+        assert_eq!(checkpoint.outputs(), schema.execute_dry_run());
+        assert_eq!(checkpoint.executed(), 2);
+        assert_eq!(
+            Ok(vec![
+                "ALTER TABLE users DROP COLUMN email;",
+                "DROP TABLE users;"
+            ]),
+            schema.rollback_from(checkpoint)
+        );
+    }
+
+    #[test]
+    fn test_stale_checkpoint() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+
+        let checkpoint = schema.execute_until(1);
+        schema.execute();
+
+        assert_eq!(
+            Err(SchemaError::StaleCheckpoint),
+            schema.rollback_from(checkpoint)
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_stale_after_apply() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+
+        let checkpoint = schema.execute_until(1);
+        let fresh = schema.execute_until(1);
+        schema.apply().unwrap();
+
+        assert_eq!(
+            Err(SchemaError::StaleCheckpoint),
+            schema.rollback_from(checkpoint)
+        );
+        assert_eq!(
+            Err(SchemaError::StaleCheckpoint),
+            schema.rollback_from(fresh)
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_stale_after_mutation() {
+        let mutations: [fn(&mut Schema); 5] = [
+            |schema| schema.truncate(1),
+            |schema| {
+                schema.remove_migration(0);
+            },
+            |schema| schema.swap(0, 2).unwrap(),
+            |schema| schema.insert_migration(0, Box::new(add_field())).unwrap(),
+            |schema| schema.clear(),
+        ];
+
+        for mutate in mutations {
+            let mut schema = SchemaBuilder::new()
+                .add(create_table())
+                .add(add_field())
+                .add(*Step::new("create posts", &[]))
+                .build();
+
+            let checkpoint = schema.execute_until(3);
+            mutate(&mut schema);
+
+            assert_eq!(
+                Err(SchemaError::StaleCheckpoint),
+                schema.rollback_from(checkpoint)
+            );
+        }
+    }
+
+    #[test]
+    fn test_apply_is_idempotent() {
+        let runs = Rc::new(Cell::new(0));
+        let mut schema = Schema::default();
+
+        schema.add_migration(Flaky::new("create_table:users", &runs, false));
+        schema.add_migration(Flaky::new("add_field:users.email", &runs, false));
+
+        assert_eq!(schema.apply().unwrap().len(), 2);
+        assert_eq!(Ok(Vec::new()), schema.apply());
+        assert_eq!(runs.get(), 2);
+        assert_eq!(
+            schema.applied(),
+            vec!["create_table:users", "add_field:users.email"]
+        );
+    }
+
+    #[test]
+    fn test_mark_applied() {
+        let runs = Rc::new(Cell::new(0));
+        let mut schema = Schema::default();
+
+        schema.add_migration(Flaky::new("create_table:users", &runs, false));
+        schema.add_migration(Flaky::new("add_field:users.email", &runs, false));
+        schema.add_migration(Flaky::new("add_index", &runs, false));
+        schema.mark_applied("create_table:users");
+
+        assert_eq!(
+            Ok(vec![
+                String::from("add_field:users.email"),
+                String::from("add_index")
+            ]),
+            schema.apply()
+        );
+        assert_eq!(runs.get(), 2);
+        assert_eq!(
+            schema.applied(),
+            vec!["create_table:users", "add_field:users.email", "add_index"]
+        );
+
+        let restored = MigrationHistory::from_json(&schema.history().to_json()).unwrap();
+        assert_eq!(restored.applied(), schema.applied());
+    }
+
+    #[test]
+    fn test_failed_migration_not_applied() {
+        let runs = Rc::new(Cell::new(0));
+        let mut schema = Schema::default();
+
+        schema.add_migration(Flaky::new("create_table:users", &runs, false));
+        schema.add_migration(Flaky::new("add_field:users.email", &runs, true));
+
+        assert!(schema.apply().is_err());
+        assert_eq!(schema.applied(), vec!["create_table:users"]);
+
+        assert!(schema.apply().is_err());
+        assert_eq!(runs.get(), 3);
+    }
+
+    #[derive(Clone)]
+    struct Editable {
+        payload: &'static str,
+    }
+
+    impl Migration for Editable {
+        fn execute(&self) -> &str {
+            self.payload
+        }
+
+        fn rollback(&self) -> &str {
+            "undo"
+        }
+
+        fn name(&self) -> &str {
+            "editable"
+        }
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let mut schema = Schema::default();
+        schema.add_migration(Box::new(Editable { payload: "v1" }));
+        schema.apply().unwrap();
+
+        let history = MigrationHistory::from_json(&schema.history().to_json()).unwrap();
+        let expected = checksum("editable", "v1");
+        let actual = checksum("editable", "v2");
+        assert_eq!(history.applied_checksum("editable"), Some(expected));
+
+        let mut edited = Schema::default();
+        edited.add_migration(Box::new(Editable { payload: "v2" }));
+        edited.restore_history(history);
+
+        assert_eq!(
+            Err(MigrationError::ChecksumMismatch {
+                name: String::from("editable"),
+                expected,
+                actual,
+            }),
+            edited.apply()
+        );
+    }
+
+    #[test]
+    fn test_checksum_mismatch_forced() {
+        let mut schema = Schema::default();
+        schema.add_migration(Box::new(Editable { payload: "v1" }));
+        schema.apply().unwrap();
+
+        let mut edited = Schema::default();
+        edited.add_migration(Box::new(Editable { payload: "v2" }));
+        edited.add_migration(Box::new(create_table()));
+        edited.restore_history(schema.history().clone());
+
+        assert_eq!(
+            Ok(vec![String::from("CREATE TABLE users (id INTEGER);")]),
+            edited.apply_forced()
+        );
+        assert_eq!(Ok(Vec::new()), edited.apply());
+        assert_eq!(
+            edited.history().applied_checksum("editable"),
+            Some(checksum("editable", "v2"))
+        );
+    }
+
+    #[test]
+    fn test_schema_macro() {
+        let mut expected = Schema::default();
+
+        expected.add_migration(Box::new(create_table()));
+        expected.add_migration(Box::new(add_field()));
+
+        let schema = crate::schema![create_table(), add_field()];
+
+        assert_eq!(expected, schema);
+        assert_eq!(expected.execute(), schema.execute());
+        assert_eq!(expected.rollback(), schema.rollback());
+        assert!(crate::schema![].is_empty());
+    }
+
+    #[test]
+    fn test_run_all() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+
+        assert_eq!(schema.execute(), schema.run_all(Direction::Up));
+        assert_eq!(schema.rollback(), schema.run_all(Direction::Down));
+    }
+
+    #[derive(Clone)]
+    struct Loud;
+
+    impl Migration for Loud {
+        fn execute(&self) -> &str {
+            "CREATE TABLE users (id INTEGER);"
+        }
+
+        fn rollback(&self) -> &str {
+            "DROP TABLE users;"
+        }
+
+        fn run(&self, direction: Direction) -> String {
+            match direction {
+                Direction::Up => String::from("CREATE TABLE"),
+                Direction::Down => String::from("DROP TABLE"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_override() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(Loud));
+        schema.add_migration(Box::new(add_field()));
+
+        assert_eq!(
+            vec!["CREATE TABLE", "ALTER TABLE users ADD COLUMN email TEXT;"],
+            schema.run_all(Direction::Up)
+        );
+        assert_eq!(
+            vec!["ALTER TABLE users DROP COLUMN email;", "DROP TABLE"],
+            schema.run_all(Direction::Down)
+        );
+    }
+
+    #[test]
+    fn test_debug_and_eq() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+
+        assert_eq!(
+            format!("{:?}", schema),
+            r#"Schema { commands: ["create_table:users", "add_field:users.email"] }"#
+        );
+
+        let mut other = Schema::default();
+        other.add_migration(Box::new(create_table()));
+        assert_ne!(schema, other);
+
+        other.add_migration(Box::new(add_field()));
+        assert_eq!(schema, other);
+    }
+
+    #[test]
+    fn test_journal_replay() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(Disabled));
+        schema.add_migration(Box::new(add_field()));
+        schema.add_migration(Step::new("add\tindex", &[]));
+
+        let mut journal = Vec::new();
+        let outputs = schema.execute_journaled(&mut journal).unwrap();
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;",
+                "add\tindex"
+            ],
+            outputs
+        );
+        assert_eq!(
+            String::from_utf8(journal.clone()).unwrap(),
+            "create_table:users\tCREATE TABLE users (id INTEGER);\nadd_field:users.email\tALTER TABLE users ADD COLUMN email TEXT;\nadd\\tindex\tadd\\tindex\n"
+        );
+        assert_eq!(schema.replay(journal.as_slice()).unwrap(), 4);
+
+        // Crash while writing the last line.
+        journal.truncate(journal.len() - 5);
+        let resume_at = schema.replay(journal.as_slice()).unwrap();
+
+        assert_eq!(resume_at, 3);
+
+        let mut resumed = Vec::new();
+        assert_eq!(
+            vec!["add\tindex"],
+            schema
+                .execute_journaled_from(resume_at, &mut resumed)
+                .unwrap()
+        );
+        assert_eq!(schema.replay(&b""[..]).unwrap(), 0);
+    }
+
+    type SharedLog = Arc<Mutex<Vec<String>>>;
+
+    #[derive(Clone)]
+    struct Timed {
+        name: &'static str,
+        delay: Duration,
+        log: SharedLog,
+    }
+
+    impl Migration for Timed {
+        fn execute(&self) -> &str {
+            self.name
+        }
+
+        fn rollback(&self) -> &str {
+            self.name
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn try_execute(&self) -> Result<String, MigrationError> {
+            thread::sleep(self.delay);
+            self.log.lock().unwrap().push(format!("up {}", self.name));
+            Ok(format!("up {}", self.name))
+        }
+
+        fn try_rollback(&self) -> Result<String, MigrationError> {
+            self.log.lock().unwrap().push(format!("down {}", self.name));
+            Ok(format!("down {}", self.name))
+        }
+    }
+
+    fn timed_schema(delay: Duration, log: &SharedLog) -> SyncSchema {
+        let mut schema = SyncSchema::default();
+
+        for (name, delay) in [
+            ("a", Duration::ZERO),
+            ("slow", delay),
+            ("b", Duration::ZERO),
+        ] {
+            schema.add_migration(Box::new(Timed {
+                name,
+                delay,
+                log: Arc::clone(log),
+            }));
+        }
+
+        schema
+    }
+
+    #[test]
+    fn test_execute_with_timeout() {
+        let log = SharedLog::default();
+        let delay = Duration::from_millis(500);
+        let schema = timed_schema(delay, &log);
+
+        let started = Instant::now();
+        assert_eq!(
+            Err(MigrationError::TimedOut {
+                name: String::from("slow")
+            }),
+            schema.execute_with_timeout(Duration::from_millis(10))
+        );
+
+        // Returned while the slow command is still sleeping on its worker.
+        assert!(started.elapsed() < delay);
+        assert_eq!(*log.lock().unwrap(), vec!["up a", "down a"]);
+    }
+
+    #[test]
+    fn test_execute_within_timeout() {
+        let log = SharedLog::default();
+        let schema = timed_schema(Duration::ZERO, &log);
+
+        assert_eq!(
+            Ok(vec![
+                String::from("up a"),
+                String::from("up slow"),
+                String::from("up b")
+            ]),
+            schema.execute_with_timeout(Duration::from_secs(5))
+        );
+
+        let log = SharedLog::default();
+        let schema = timed_schema(Duration::from_millis(20), &log);
+
+        assert!(schema.execute_with_timeout(Duration::ZERO).is_ok());
+        assert_eq!(*log.lock().unwrap(), vec!["up a", "up slow", "up b"]);
+    }
+
+    #[test]
+    fn test_idempotent_schema() {
+        let mut schema = IdempotentSchema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(create_table()));
+
+        assert!(!schema.is_applied("create_table:users"));
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "already applied: create_table:users"
+            ],
+            schema.execute()
+        );
+        assert!(schema.is_applied("create_table:users"));
+        assert_eq!(
+            vec![
+                "already applied: create_table:users",
+                "already applied: create_table:users"
+            ],
+            schema.execute()
+        );
+    }
+
+    #[derive(Clone)]
+    struct Ranked {
+        name: &'static str,
+        undo: String,
+        priority: i32,
+    }
+
+    impl Ranked {
+        fn new(name: &'static str, priority: i32) -> Box<Ranked> {
+            Box::new(Ranked {
+                name,
+                undo: format!("undo {}", name),
+                priority,
+            })
+        }
+    }
+
+    impl Migration for Ranked {
+        fn execute(&self) -> &str {
+            self.name
+        }
+
+        fn rollback(&self) -> &str {
+            &self.undo
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn priority(&self) -> i32 {
+            self.priority
+        }
+    }
+
+    fn ranked_schema() -> Schema {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Ranked::new("low", -1));
+        schema.add_migration(Ranked::new("first", 0));
+        schema.add_migration(Ranked::new("high", 5));
+        schema.add_migration(Ranked::new("second", 0));
+        schema.add_migration(Ranked::new("third", 0));
+
+        schema
+    }
+
+    #[test]
+    fn test_execute_by_priority() {
+        let schema = ranked_schema();
+
+        assert_eq!(
+            vec!["high", "first", "second", "third", "low"],
+            schema.execute_by_priority()
+        );
+        assert_eq!(
+            vec!["low", "first", "high", "second", "third"],
+            schema.execute()
+        );
+    }
+
+    #[test]
+    fn test_sorted_plan_keeps_ties_in_insertion_order() {
+        let mut schema = ranked_schema();
+        schema.add_migration(Box::new(Disabled));
+
+        let plan = schema.sorted_plan();
+
+        assert_eq!(
+            vec![2, 1, 3, 4, 5, 0],
+            plan.iter().map(|entry| entry.index).collect::<Vec<_>>()
+        );
+        assert_eq!(plan[4].action, PlanAction::Skip);
+        assert_eq!(
+            vec!["high", "first", "second", "third", "low"],
+            schema.execute_by_priority()
+        );
+    }
+
+    #[test]
+    fn test_rollback_by_priority() {
+        let schema = ranked_schema();
+
+        let mut executed = schema.execute_by_priority();
+        executed.reverse();
+
+        let rolled_back = schema.rollback_by_priority();
+
+        assert_eq!(
+            vec![
+                "undo low",
+                "undo third",
+                "undo second",
+                "undo first",
+                "undo high"
+            ],
+            rolled_back
+        );
+        assert_eq!(
+            executed,
+            rolled_back
+                .iter()
+                .map(|output| output.trim_start_matches("undo "))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_verify_symmetry() {
+        let mut schema = Schema::default();
+
+        assert!(schema.verify_symmetry());
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+
+        assert!(schema.verify_symmetry());
+
+        schema.add_migration(Box::new(CompositeMigration::new(
+            "composite",
+            vec![Step::new("a", &[]), Step::new("b", &[])],
+        )));
+
+        assert!(schema.verify_symmetry());
+    }
+
+    #[derive(Clone)]
+    struct Lopsided;
+
+    impl Migration for Lopsided {
+        fn execute(&self) -> &str {
+            "a; b"
+        }
+
+        fn rollback(&self) -> &str {
+            "undo a; undo b"
+        }
+
+        fn execute_trace(&self) -> Vec<&str> {
+            vec!["a", "b"]
+        }
+    }
+
+    #[test]
+    fn test_verify_symmetry_detects_broken_composite() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(Lopsided));
+
+        assert!(!schema.verify_symmetry());
+    }
+
+    fn keyed_schema() -> KeyedSchema {
+        let mut schema = KeyedSchema::default();
+
+        schema.add("users", Box::new(create_table())).unwrap();
+        schema.add("email", Box::new(add_field())).unwrap();
+        schema.add("posts", Step::new("create posts", &[])).unwrap();
+
+        schema
+    }
+
+    #[test]
+    fn test_keyed_schema_rejects_duplicates() {
+        let mut schema = keyed_schema();
+
+        assert_eq!(
+            Err(SchemaError::DuplicateKey {
+                key: String::from("users")
+            }),
+            schema.add("users", Box::new(add_field()))
+        );
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;",
+                "create posts"
+            ],
+            schema.execute_all()
+        );
+    }
+
+    #[test]
+    fn test_keyed_schema_execute_one() {
+        let schema = keyed_schema();
+
+        assert_eq!(
+            Some(String::from("ALTER TABLE users ADD COLUMN email TEXT;")),
+            schema.execute_one("email")
+        );
+        assert_eq!(None, schema.execute_one("missing"));
+    }
+
+    #[test]
+    fn test_keyed_schema_execute_one_disabled() {
+        let mut schema = keyed_schema();
+        schema.add("disabled", Box::new(Disabled)).unwrap();
+
+        assert_eq!(None, schema.execute_one("disabled"));
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;",
+                "create posts"
+            ],
+            schema.execute_all()
+        );
+    }
+
+    #[test]
+    fn test_keyed_schema_remove() {
+        let mut schema = keyed_schema();
+
+        assert_eq!(
+            Some("add_field:users.email"),
+            schema.remove("email").as_deref().map(|cmd| cmd.name())
+        );
+        assert!(schema.remove("email").is_none());
+        assert_eq!(vec!["users", "posts"], schema.keys().collect::<Vec<_>>());
+        assert_eq!(
+            vec!["CREATE TABLE users (id INTEGER);", "create posts"],
+            schema.execute_all()
+        );
+    }
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn test_sync_schema_is_send_and_sync() {
+        assert_send::<SyncSchema>();
+        assert_sync::<SyncSchema>();
+    }
+
+    #[test]
+    fn test_sync_schema_across_threads() {
+        let mut schema = SyncSchema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(add_field()));
+
+        let (sender, receiver) = mpsc::channel();
+        let worker = thread::spawn(move || {
+            let outputs: Vec<String> = schema.execute().into_iter().map(String::from).collect();
+            sender.send(outputs).unwrap();
+            schema
+        });
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ],
+            receiver.recv().unwrap()
+        );
+
+        let schema = worker.join().unwrap();
+        assert_eq!(2, schema.len());
+        assert_eq!(
+            vec!["ALTER TABLE users DROP COLUMN email;", "DROP TABLE users;"],
+            schema.rollback()
+        );
+    }
+
+    #[derive(Clone)]
+    struct StuckRollback;
+
+    impl Migration for StuckRollback {
+        fn execute(&self) -> &str {
+            "stuck"
+        }
+
+        fn rollback(&self) -> &str {
+            "unstick"
+        }
+
+        fn name(&self) -> &str {
+            "stuck"
+        }
+
+        fn try_rollback(&self) -> Result<String, MigrationError> {
+            Err(MigrationError::Failed {
+                name: String::from("stuck"),
+                reason: String::from("locked"),
+            })
+        }
+    }
+
+    fn stuck_schema(log: &Log) -> Schema {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Logged::new("a", log, false));
+        schema.add_migration(Box::new(StuckRollback));
+        schema.add_migration(Logged::new("b", log, false));
+
+        schema
+    }
+
+    #[test]
+    fn test_rollback_checked() {
+        let log = Log::default();
+        let report = stuck_schema(&log).rollback_checked();
+
+        let results: Vec<_> = report
+            .entries()
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.result.is_ok()))
+            .collect();
+
+        assert_eq!(vec![("b", true), ("stuck", false), ("a", true)], results);
+        assert!(!report.is_success());
+        assert_eq!(
+            vec!["stuck"],
+            report
+                .failures()
+                .map(|entry| entry.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(*log.borrow(), vec!["down b", "down a"]);
+    }
+
+    #[test]
+    fn test_rollback_checked_strict() {
+        let log = Log::default();
+        let report = stuck_schema(&log).rollback_checked_strict();
+
+        let results: Vec<_> = report
+            .entries()
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.result.is_ok()))
+            .collect();
+
+        assert_eq!(vec![("b", true), ("stuck", false)], results);
+        assert_eq!(*log.borrow(), vec!["down b"]);
+
+        let mut schema = Schema::default();
+        schema.add_migration(Box::new(create_table()));
+
+        assert!(schema.rollback_checked_strict().is_success());
+    }
+
+    #[test]
+    fn test_merge_keyed_schemas() {
+        let mut other = KeyedSchema::default();
+        other
+            .add("comments", Step::new("create comments", &[]))
+            .unwrap();
+
+        let mut schema = keyed_schema().merge(other).unwrap();
+
+        assert_eq!(
+            vec!["users", "email", "posts", "comments"],
+            schema.keys().collect::<Vec<_>>()
+        );
+
+        let mut clashing = KeyedSchema::default();
+        clashing.add("tags", Box::new(create_table())).unwrap();
+        clashing.add("email", Box::new(add_field())).unwrap();
+
+        assert_eq!(
+            Err(SchemaError::DuplicateKey {
+                key: String::from("email")
+            }),
+            schema.append(clashing)
+        );
+        assert_eq!(4, schema.keys().count());
+    }
+
+    struct CreateUsers;
+
+    impl CtxMigration for CreateUsers {
+        fn execute(&self, ctx: &mut ExecutionContext) -> Result<String, MigrationError> {
+            ctx.insert("table", "users");
+            Ok(String::from("create table users"))
+        }
+
+        fn name(&self) -> &str {
+            "create_users"
+        }
+    }
+
+    struct AddEmail;
+
+    impl CtxMigration for AddEmail {
+        fn execute(&self, ctx: &mut ExecutionContext) -> Result<String, MigrationError> {
+            let table = ctx.get("table").ok_or_else(|| MigrationError::Failed {
+                name: self.name().to_string(),
+                reason: String::from("missing context key: table"),
+            })?;
+
+            Ok(format!("add field email to {}", table))
+        }
+
+        fn name(&self) -> &str {
+            "add_email"
+        }
+    }
+
+    #[test]
+    fn test_apply_with_ctx() {
+        let mut schema = CtxSchema::default();
+
+        schema.add_migration(Box::new(CreateUsers));
+        schema.add_migration(Box::new(AddEmail));
+
+        let (outputs, ctx) = schema.apply_with_ctx().unwrap();
+
+        assert_eq!(
+            vec!["create table users", "add field email to users"],
+            outputs
+        );
+        assert_eq!(Some("users"), ctx.get("table"));
+    }
+
+    #[test]
+    fn test_apply_with_ctx_missing_key() {
+        let mut schema = CtxSchema::default();
+
+        schema.add_migration(Box::new(AddEmail));
+        schema.add_migration(Box::new(CreateUsers));
+
+        assert_eq!(
+            Err(MigrationError::Failed {
+                name: String::from("add_email"),
+                reason: String::from("missing context key: table"),
+            }),
+            schema.apply_with_ctx()
+        );
+    }
+
+    #[derive(Clone)]
+    struct Unreliable {
+        name: &'static str,
+        failures: Cell<u32>,
+        transient: bool,
+    }
+
+    impl Unreliable {
+        fn new(name: &'static str, failures: u32, transient: bool) -> Box<Self> {
+            Box::new(Unreliable {
+                name,
+                failures: Cell::new(failures),
+                transient,
+            })
+        }
+    }
+
+    impl Migration for Unreliable {
+        fn execute(&self) -> &str {
+            self.name
+        }
+
+        fn rollback(&self) -> &str {
+            self.name
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn try_execute(&self) -> Result<String, MigrationError> {
+            if self.failures.get() == 0 {
+                return Ok(format!("up {}", self.name));
+            }
+
+            self.failures.set(self.failures.get() - 1);
+
+            let name = self.name.to_string();
+            let reason = String::from("connection reset");
+
+            if self.transient {
+                Err(MigrationError::Transient { name, reason })
+            } else {
+                Err(MigrationError::Failed { name, reason })
+            }
+        }
+    }
+
+    const RETRY: RetryPolicy = RetryPolicy {
+        max_attempts: 3,
+        delay: Duration::ZERO,
+    };
+
+    #[test]
+    fn test_apply_with_retry() {
+        let log = Log::default();
+        let mut schema = Schema::default();
+
+        schema.add_migration(Logged::new("a", &log, false));
+        schema.add_migration(Unreliable::new("flaky", 2, true));
+
+        let report = schema.apply_with_retry(RETRY);
+
+        assert!(report.is_success());
+        assert_eq!(3, report.attempts_for("flaky"));
+        assert_eq!(
+            vec![("a", 1), ("flaky", 1), ("flaky", 2), ("flaky", 3)],
+            report
+                .attempts()
+                .iter()
+                .map(|attempt| (attempt.name.as_str(), attempt.attempt))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Ok(String::from("up flaky")), report.attempts()[3].result);
+        assert_eq!(*log.borrow(), vec!["up a"]);
+    }
+
+    #[test]
+    fn test_apply_with_retry_permanent_failure() {
+        let log = Log::default();
+        let mut schema = Schema::default();
+
+        schema.add_migration(Logged::new("a", &log, false));
+        schema.add_migration(Unreliable::new("broken", 1, false));
+        schema.add_migration(Logged::new("b", &log, false));
+
+        let report = schema.apply_with_retry(RETRY);
+
+        assert_eq!(1, report.attempts_for("broken"));
+        assert!(matches!(
+            report.error(),
+            Some(MigrationError::Failed { .. })
+        ));
+        assert_eq!(vec!["a"], report.rolled_back());
+        assert_eq!(*log.borrow(), vec!["up a", "down a"]);
+    }
+
+    #[test]
+    fn test_apply_with_retry_exhausted() {
+        let log = Log::default();
+        let mut schema = Schema::default();
+
+        schema.add_migration(Logged::new("a", &log, false));
+        schema.add_migration(Unreliable::new("flaky", 5, true));
+
+        let report = schema.apply_with_retry(RETRY);
+
+        assert_eq!(3, report.attempts_for("flaky"));
+        assert_eq!(
+            Some(&MigrationError::Transient {
+                name: String::from("flaky"),
+                reason: String::from("connection reset"),
+            }),
+            report.error()
+        );
+        assert_eq!(vec!["a"], report.rolled_back());
+        assert_eq!(*log.borrow(), vec!["up a", "down a"]);
+    }
+
+    fn recording_hooks(events: &Log) -> SchemaHooks {
+        let before = Rc::clone(events);
+        let after = Rc::clone(events);
+        let error = Rc::clone(events);
+        let complete = Rc::clone(events);
+
+        SchemaHooks::new()
+            .before_each(move |name| before.borrow_mut().push(format!("before {}", name)))
+            .after_each(move |name, output| {
+                after
+                    .borrow_mut()
+                    .push(format!("after {}: {}", name, output))
+            })
+            .on_error(move |name, err| error.borrow_mut().push(format!("error {}: {}", name, err)))
+            .on_complete(move |summary: &RunSummary| {
+                complete.borrow_mut().push(format!(
+                    "complete [{}] failed {:?}",
+                    summary.executed.join(", "),
+                    summary.failed
+                ))
+            })
+    }
+
+    #[test]
+    fn test_hooks_on_apply() {
+        let log = Log::default();
+        let events = Log::default();
+        let mut schema = Schema::default();
+
+        schema.add_migration(Logged::new("a", &log, false));
+        schema.add_migration(Logged::new("b", &log, true));
+        schema.add_migration(Logged::new("c", &log, false));
+        schema.set_hooks(recording_hooks(&events));
+
+        assert!(schema.apply().is_err());
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                "before a",
+                "after a: up a",
+                "before b",
+                "error b: migration b failed: boom",
+                "complete [a] failed Some(\"b\")",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hooks_on_execute() {
+        let events = Log::default();
+        let mut schema = SchemaBuilder::new()
+            .add(create_table())
+            .add(add_field())
+            .build();
+
+        schema.set_hooks(recording_hooks(&events));
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ],
+            schema.execute()
+        );
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                "before create_table:users",
+                "after create_table:users: CREATE TABLE users (id INTEGER);",
+                "before add_field:users.email",
+                "after add_field:users.email: ALTER TABLE users ADD COLUMN email TEXT;",
+                "complete [create_table:users, add_field:users.email] failed None",
+            ]
+        );
+    }
+
+    #[derive(Clone)]
+    struct UsersTable;
+
+    impl Migration for UsersTable {
+        fn execute(&self) -> &str {
+            "create table users"
+        }
+
+        fn rollback(&self) -> &str {
+            "drop table users"
+        }
+
+        fn name(&self) -> &str {
+            "users_table"
+        }
+
+        fn apply_state(&self, state: &mut SchemaState) {
+            state.create_table("users");
+        }
+
+        fn revert_state(&self, state: &mut SchemaState) {
+            state.drop_table("users");
+        }
+    }
+
+    fn guarded_schema() -> Schema {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(GuardedMigration::new(
+            Box::new(UsersTable),
+            Box::new(|state: &SchemaState| !state.has_table("users")),
+        )));
+        schema.add_migration(Box::new(add_field()));
+
+        schema
+    }
+
+    #[test]
+    fn test_guarded_migration() {
+        let schema = guarded_schema();
+        let mut state = SchemaState::new();
+
+        let first = schema.apply_with_state(&mut state);
+
+        assert_eq!(
+            Some(&ApplyStatus::Applied(String::from("create table users"))),
+            first.status("users_table")
+        );
+        assert!(state.has_table("users"));
+
+        let second = schema.apply_with_state(&mut state);
+
+        assert_eq!(Some(&ApplyStatus::Skipped), second.status("users_table"));
+        assert_eq!(
+            Some(&ApplyStatus::Applied(String::from(
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ))),
+            second.status("add_field:users.email")
+        );
+    }
+
+    #[test]
+    fn test_revert_skips_guarded_migration() {
+        let schema = guarded_schema();
+        let mut state = SchemaState::new();
+        state.create_table("users");
+
+        let report = schema.apply_with_state(&mut state);
+
+        assert_eq!(
+            vec!["ALTER TABLE users DROP COLUMN email;"],
+            schema.revert_with_state(&report, &mut state)
+        );
+        assert!(state.has_table("users"));
+
+        let mut state = SchemaState::new();
+        let report = schema.apply_with_state(&mut state);
+
+        assert_eq!(
+            vec!["ALTER TABLE users DROP COLUMN email;", "drop table users"],
+            schema.revert_with_state(&report, &mut state)
+        );
+        assert_eq!(0, state.tables().count());
+    }
+
+    #[derive(Clone)]
+    struct NoRollback;
+
+    impl Migration for NoRollback {
+        fn execute(&self) -> &str {
+            "create index"
+        }
+
+        fn rollback(&self) -> &str {
+            ""
+        }
+
+        fn name(&self) -> &str {
+            "no_rollback"
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = SchemaBuilder::new()
+            .add(create_table())
+            .add(add_field())
+            .build();
+
+        assert_eq!(Ok(()), schema.validate());
+    }
+
+    #[test]
+    fn test_validate_runs_nothing() {
+        let runs = Rc::new(Cell::new(0));
+        let schema = SchemaBuilder::new()
+            .add(Counter {
+                runs: Rc::clone(&runs),
+            })
+            .build();
+
+        assert_eq!(Ok(()), schema.validate());
+        assert_eq!(0, runs.get());
+    }
+
+    #[test]
+    fn test_validate_reports_broken_rollbacks() {
+        let schema = SchemaBuilder::new()
+            .add(create_table())
+            .add(NoRollback)
+            .add(*Step::new("same both ways", &[]))
+            .build();
+
+        assert_eq!(
+            Err(vec![
+                String::from("no_rollback"),
+                String::from("same both ways")
+            ]),
+            schema.validate()
+        );
+    }
+
+    fn three_step_schema() -> Schema {
+        SchemaBuilder::new()
+            .add(create_table())
+            .add(add_field())
+            .add(*Step::new("create posts", &[]))
+            .build()
+    }
+
+    #[test]
+    fn test_names_and_truncate() {
+        let mut schema = three_step_schema();
+
+        assert_eq!(3, schema.len());
+        assert_eq!(
+            vec![
+                "create_table:users",
+                "add_field:users.email",
+                "create posts"
+            ],
+            schema.names()
+        );
+
+        schema.truncate(5);
+        assert_eq!(3, schema.len());
+
+        schema.truncate(1);
+        assert_eq!(vec!["create_table:users"], schema.names());
+
+        schema.truncate(0);
+        assert!(schema.is_empty());
+    }
+
+    #[test]
+    fn test_clear_keeps_history() {
+        let mut schema = three_step_schema();
+        schema.mark_applied("create_table:users");
+
+        schema.clear();
+
+        assert!(schema.is_empty());
+        assert_eq!(vec!["create_table:users"], schema.applied());
+    }
+
+    #[test]
+    fn test_clear_pending() {
+        let mut schema = three_step_schema();
+        schema.mark_applied("add_field:users.email");
+
+        schema.clear_pending();
+
+        assert_eq!(vec!["add_field:users.email"], schema.names());
+        assert_eq!(vec!["add_field:users.email"], schema.applied());
+    }
+
+    #[test]
+    fn test_clear_all() {
+        let mut schema = three_step_schema();
+        schema.mark_applied("add_field:users.email");
+
+        schema.clear_all();
+
+        assert!(schema.is_empty());
+        assert!(schema.applied().is_empty());
+    }
+
+    #[test]
+    fn test_versioned_clear() {
+        let mut schema = VersionedSchema::default();
+        schema.add_migration(1, Box::new(create_table())).unwrap();
+        schema.add_migration(2, Box::new(add_field())).unwrap();
+        schema.execute();
+        schema
+            .add_migration(3, Step::new("create posts", &[]))
+            .unwrap();
+
+        schema.clear_pending();
+
+        assert_eq!(2, schema.current_version());
+        assert!(schema.execute().is_empty());
+        assert_eq!(
+            vec!["ALTER TABLE users DROP COLUMN email;", "DROP TABLE users;"],
+            schema.rollback_to(0)
+        );
+
+        schema.execute();
+        schema.clear_all();
+
+        assert_eq!(0, schema.current_version());
+        assert!(schema.execute().is_empty());
+    }
+
+    #[test]
+    fn test_versioned_max_version() {
+        let mut schema = VersionedSchema::default();
+        schema.add_migration(1, Box::new(create_table())).unwrap();
+        schema
+            .add_migration(u64::MAX, Box::new(add_field()))
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ],
+            schema.execute()
+        );
+        assert_eq!(u64::MAX, schema.current_version());
+        assert!(schema.execute().is_empty());
+
+        schema.clear_pending();
+
+        assert_eq!(
+            vec!["ALTER TABLE users DROP COLUMN email;"],
+            schema.rollback_to(1)
+        );
+        assert_eq!(
+            vec!["ALTER TABLE users ADD COLUMN email TEXT;"],
+            schema.execute()
+        );
+    }
+
+    #[test]
+    fn test_clone_schema() {
+        let original = SchemaBuilder::new().add(create_table()).build();
+        let mut snapshot = original.clone();
+
+        snapshot.add_migration(Box::new(add_field()));
+        snapshot.add_migration(Box::new(CompositeMigration::new(
+            "composite",
+            vec![Step::new("a", &[]), Step::new("b", &[])],
+        )));
+
+        assert_eq!(vec!["CREATE TABLE users (id INTEGER);"], original.execute());
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;",
+                "a; b"
+            ],
+            snapshot.execute()
+        );
+    }
+
+    #[test]
+    fn test_clone_box() {
+        let runs = Rc::new(Cell::new(0));
+        let migration: Box<dyn Migration> = Box::new(Counter {
+            runs: Rc::clone(&runs),
+        });
+
+        let copy = migration.clone_box();
+
+        assert_eq!(migration.name(), copy.name());
+        assert_eq!(migration.execute(), copy.execute());
+        // The copy is a separate migration, but clones of an `Rc` still share its target.
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[test]
+    fn test_default_metadata() {
+        assert_eq!(Metadata::default(), Step::new("step", &[]).metadata());
+        assert_eq!(String::from("w2u2u"), create_table().metadata().author);
+    }
+
+    #[test]
+    fn test_report() {
+        let schema = SchemaBuilder::new()
+            .add(create_table())
+            .add(add_field())
+            .add(*Step::new("backfill", &[]))
+            .build();
+
+        assert_eq!(
+            schema.report(),
+            [
+                "name                  | author | created_at | description",
+                "create_table:users    | w2u2u  | 1700000000 | Creates the table",
+                "add_field:users.email | w2u2u  | 1700086400 | Adds a field to the table",
+                "backfill              | -      | -          | -",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_filter_by_author() {
+        let schema = SchemaBuilder::new()
+            .add(create_table())
+            .add(*Step::new("backfill", &[]))
+            .add(add_field())
+            .build();
+
+        assert_eq!(
+            vec!["create_table:users", "add_field:users.email"],
+            schema
+                .filter_by_author("w2u2u")
+                .iter()
+                .map(|cmd| cmd.name())
+                .collect::<Vec<_>>()
+        );
+        assert!(schema.filter_by_author("someone else").is_empty());
+    }
+
+    fn interactive_schema() -> Schema {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(Disabled));
+        schema.add_migration(Box::new(add_field()));
+        schema.add_migration(Step::new("add_index", &[]));
+        schema
+    }
+
+    #[test]
+    fn test_run_interactive() {
+        let schema = interactive_schema();
+        let mut output = Vec::new();
+
+        let run = schema
+            .run_interactive("y\nn\nY\n".as_bytes(), &mut output)
+            .unwrap();
+
+        assert_eq!(run.applied, vec!["create_table:users", "add_index"]);
+        assert!(run.rolled_back.is_empty());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "plan:\n  1. create_table:users (execute)\n  2. unnamed (skip)\n  3. add_field:users.email (execute)\n  \
+             4. add_index (execute)\n\
+             next: create_table:users\napply next? [y/n/q] CREATE TABLE users (id INTEGER);\n\
+             next: add_field:users.email\napply next? [y/n/q] skipped add_field:users.email\n\
+             next: add_index\napply next? [y/n/q] add_index\n"
+        );
+    }
+
+    #[test]
+    fn test_run_interactive_quit_rolls_back() {
+        let schema = interactive_schema();
+        let mut output = Vec::new();
+
+        let run = schema
+            .run_interactive("y\nmaybe\ny\nq\ny\n".as_bytes(), &mut output)
+            .unwrap();
+
+        assert_eq!(
+            run.applied,
+            vec!["create_table:users", "add_field:users.email"]
+        );
+        assert_eq!(
+            run.rolled_back,
+            vec!["add_field:users.email", "create_table:users"]
+        );
+        assert!(String::from_utf8(output).unwrap().ends_with(
+            "apply next? [y/n/q] roll back applied commands? [y/n] ALTER TABLE users DROP COLUMN email;\nDROP TABLE users;\n"
+        ));
+    }
+
+    #[test]
+    fn test_run_interactive_quit_keeps_applied() {
+        let schema = interactive_schema();
+
+        let declined = schema
+            .run_interactive("y\nq\nn\n".as_bytes(), &mut Vec::new())
+            .unwrap();
+        assert_eq!(declined.applied, vec!["create_table:users"]);
+        assert!(declined.rolled_back.is_empty());
+
+        // Running out of input is the same as quitting without answering.
+        let eof = schema
+            .run_interactive("y\n".as_bytes(), &mut Vec::new())
+            .unwrap();
+        assert_eq!(eof.applied, vec!["create_table:users"]);
+        assert!(eof.rolled_back.is_empty());
+    }
+
+    crate::impl_migration!(CreateOrders, "create table orders", "drop table orders");
+    crate::impl_migration!(
+        RenameColumn { table: &'static str, from: &'static str, to: &'static str },
+        |s| format!("rename {}.{} to {}", s.table, s.from, s.to),
+        |s| format!("rename {}.{} to {}", s.table, s.to, s.from),
+    );
+
+    #[test]
+    fn test_impl_migration() {
+        let mut schema = Schema::new();
+
+        schema.add_migration(Box::new(CreateOrders));
+        schema.add_migration(Box::new(RenameColumn::new("orders", "qty", "quantity")));
+
+        assert_eq!(schema.names(), vec!["CreateOrders", "RenameColumn"]);
+        assert_eq!(
+            schema.execute(),
+            vec!["create table orders", "rename orders.qty to quantity"]
+        );
+        assert_eq!(
+            schema.rollback(),
+            vec!["rename orders.quantity to qty", "drop table orders"]
+        );
+    }
+
+    #[derive(Clone)]
+    struct TableStep {
+        table: &'static str,
+        fail: bool,
+        rollbacks: Rc<Cell<u32>>,
+    }
+
+    impl TableStep {
+        fn new(table: &'static str, fail: bool, rollbacks: &Rc<Cell<u32>>) -> Box<Self> {
+            Box::new(TableStep {
+                table,
+                fail,
+                rollbacks: Rc::clone(rollbacks),
+            })
+        }
+    }
+
+    impl Migration for TableStep {
+        fn execute(&self) -> &str {
+            self.table
+        }
+
+        fn rollback(&self) -> &str {
+            self.rollbacks.set(self.rollbacks.get() + 1);
+            self.table
+        }
+
+        fn name(&self) -> &str {
+            self.table
+        }
+
+        fn try_execute(&self) -> Result<String, MigrationError> {
+            if self.fail {
+                Err(MigrationError::Failed {
+                    name: self.table.to_string(),
+                    reason: String::from("boom"),
+                })
+            } else {
+                Ok(self.execute().to_string())
+            }
+        }
+
+        fn apply_state(&self, state: &mut SchemaState) {
+            state.create_table(self.table);
+        }
+
+        fn revert_state(&self, state: &mut SchemaState) {
+            state.drop_table(self.table);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_schema_rollback_strategies() {
+        let rollbacks = Rc::new(Cell::new(0));
+        let mut schema = SnapshotSchema::new();
+
+        schema.add_migration(TableStep::new("users", false, &rollbacks));
+        schema.add_migration(TableStep::new("orders", false, &rollbacks));
+        schema.add_migration(TableStep::new("invoices", true, &rollbacks));
+        schema.add_migration(TableStep::new("payments", false, &rollbacks));
+
+        let mut initial = SchemaState::new();
+        initial.create_table("accounts");
+
+        let mut per_command = initial.clone();
+        let err = schema
+            .apply(&mut per_command, RollbackStrategy::PerCommand)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "migration invoices failed: boom");
+        assert_eq!(per_command, initial);
+        assert_eq!(rollbacks.get(), 2);
+
+        rollbacks.set(0);
+        let mut snapshot = initial.clone();
+        schema
+            .apply(&mut snapshot, RollbackStrategy::Snapshot)
+            .unwrap_err();
+        assert_eq!(snapshot, initial);
+        assert_eq!(rollbacks.get(), 0);
+    }
+
+    #[test]
+    fn test_snapshot_schema_rollback_fails() {
+        let rollbacks = Rc::new(Cell::new(0));
+        let mut schema = SnapshotSchema::new();
+
+        schema.add_migration(TableStep::new("users", false, &rollbacks));
+        schema.add_migration(Box::new(StuckRollback));
+        schema.add_migration(TableStep::new("orders", false, &rollbacks));
+        schema.add_migration(TableStep::new("invoices", true, &rollbacks));
+
+        let mut per_command = SchemaState::new();
+        let err = schema
+            .apply(&mut per_command, RollbackStrategy::PerCommand)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "migration stuck failed: locked");
+        assert_eq!(per_command.tables().collect::<Vec<_>>(), vec!["users"]);
+        assert_eq!(rollbacks.get(), 1);
+
+        let mut snapshot = SchemaState::new();
+        let err = schema
+            .apply(&mut snapshot, RollbackStrategy::Snapshot)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "migration invoices failed: boom");
+        assert_eq!(snapshot, SchemaState::new());
+    }
+
+    #[test]
+    fn test_snapshot_schema_success() {
+        let rollbacks = Rc::new(Cell::new(0));
+        let mut schema = SnapshotSchema::default();
+
+        schema.add_migration(TableStep::new("users", false, &rollbacks));
+        schema.add_migration(TableStep::new("orders", false, &rollbacks));
+
+        let mut state = SchemaState::new();
+        let outputs = schema
+            .apply(&mut state, RollbackStrategy::Snapshot)
+            .unwrap();
+
+        assert_eq!(outputs, vec!["users", "orders"]);
+        assert_eq!(state.tables().collect::<Vec<_>>(), vec!["orders", "users"]);
+    }
+
+    #[test]
+    fn test_apply_transactional_commits() {
+        let rollbacks = Rc::new(Cell::new(0));
+        let mut schema = Schema::new();
+
+        schema.add_migration(TableStep::new("users", false, &rollbacks));
+        schema.add_migration(TableStep::new("orders", false, &rollbacks));
+
+        let mut state = SchemaState::new();
+        let report = schema.apply_transactional(&mut state);
+
+        assert_eq!(report.status, TransactionStatus::Committed);
+        assert_eq!(report.rollback_steps, 0);
+        assert_eq!(report.failed(), None);
+        assert_eq!(report.entries.len(), 2);
+        assert!(state.has_table("users") && state.has_table("orders"));
+        assert_eq!(rollbacks.get(), 0);
+    }
+
+    #[test]
+    fn test_apply_transactional_rolls_back() {
+        let rollbacks = Rc::new(Cell::new(0));
+        let mut schema = Schema::new();
+
+        schema.add_migration(TableStep::new("users", false, &rollbacks));
+        schema.add_migration(TableStep::new("orders", false, &rollbacks));
+        schema.add_migration(TableStep::new("invoices", true, &rollbacks));
+        schema.add_migration(TableStep::new("payments", false, &rollbacks));
+
+        let mut state = SchemaState::new();
+        let mut report = schema.apply_transactional(&mut state);
+
+        assert_eq!(report.status, TransactionStatus::RolledBack);
+        assert_eq!(report.rollback_steps, 2);
+        assert_eq!(
+            report.failed().map(|entry| entry.name.as_str()),
+            Some("invoices")
+        );
+        assert_eq!(report.entries.len(), 3);
+        assert_eq!(state, SchemaState::new());
+        assert_eq!(rollbacks.get(), 2);
+
+        for entry in &mut report.entries {
+            entry.duration = Duration::from_millis(1);
+        }
+        assert_eq!(
+            report.to_string(),
+            "rolled back after invoices failed, 2 rollback steps, 0 failed\n  \
+             ok     users (1ms): users\n  \
+             ok     orders (1ms): orders\n  \
+             failed invoices (1ms): migration invoices failed: boom\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_transactional_rollback_fails() {
+        let rollbacks = Rc::new(Cell::new(0));
+        let mut schema = Schema::new();
+
+        schema.add_migration(TableStep::new("users", false, &rollbacks));
+        schema.add_migration(Box::new(StuckRollback));
+        schema.add_migration(TableStep::new("invoices", true, &rollbacks));
+
+        let mut state = SchemaState::new();
+        let mut report = schema.apply_transactional(&mut state);
+
+        assert_eq!(report.status, TransactionStatus::RollbackFailed);
+        assert_eq!(report.rollback_steps, 1);
+        assert_eq!(
+            report.rollback_failures,
+            vec![RollbackEntry {
+                name: String::from("stuck"),
+                result: Err(MigrationError::Failed {
+                    name: String::from("stuck"),
+                    reason: String::from("locked"),
+                }),
+            }]
+        );
+        assert_eq!(state, SchemaState::new());
+        assert_eq!(rollbacks.get(), 1);
+
+        for entry in &mut report.entries {
+            entry.duration = Duration::from_millis(1);
+        }
+        assert_eq!(
+            report.to_string(),
+            "rolled back after invoices failed, 1 rollback steps, 1 failed\n  \
+             ok     users (1ms): users\n  \
+             ok     stuck (1ms): stuck\n  \
+             failed invoices (1ms): migration invoices failed: boom\n  \
+             stuck  stuck: migration stuck failed: locked\n"
+        );
+    }
+
+    #[test]
+    fn test_strategy_migration() {
+        use crate::patterns::strategy_di::{AdditionStrategy, MultiplicationStrategy};
+
+        let mut schema = Schema::new();
+
+        schema.add_migration(Box::new(StrategyMigration::new(
+            Box::new(AdditionStrategy),
+            4,
+            5,
+        )));
+        schema.add_migration(Box::new(StrategyMigration::new(
+            Box::new(MultiplicationStrategy),
+            4,
+            5,
+        )));
+
+        assert_eq!(schema.execute(), vec!["9", "20"]);
+        assert_eq!(
+            schema.rollback(),
+            vec!["discard computed value", "discard computed value"]
+        );
+    }
+
+    #[test]
+    fn test_plan_document() {
+        let runs = Rc::new(Cell::new(0));
+        let mut schema = Schema::new();
+
+        schema.add_migration(Box::new(create_table()));
+        schema.add_migration(Box::new(Disabled));
+        schema.add_migration(Box::new(add_field()));
+        schema.add_migration(Box::new(Counter {
+            runs: Rc::clone(&runs),
+        }));
+
+        let plan = schema.plan_document();
+
+        assert_eq!(plan.steps().len(), 3);
+        assert_eq!(runs.get(), 0);
+        assert_eq!(
+            plan.to_string(),
+            "1. create_table:users\n   up:   CREATE TABLE users (id INTEGER);\n   down: DROP TABLE users;\n\
+             2. add_field:users.email\n   up:   ALTER TABLE users ADD COLUMN email TEXT;\n   down: ALTER TABLE users DROP COLUMN email;\n\
+             3. counter\n   up:   increment\n   down: decrement\n"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_function_pointer {
+    use super::function_pointer::{FallibleSchema, FnPtr, PartialRun, Schema};
+    use super::SchemaError;
+
+    fn add_field() -> String {
+        String::from("add field")
+    }
+
+    fn remove_field() -> String {
+        String::from("remove field")
     }
 
     #[test]
@@ -234,7 +6916,142 @@ mod test_function_pointer {
         schema.add_migration(add_field, remove_field);
 
         assert_eq!(vec!["create table", "add field"], schema.execute());
-        assert_eq!(vec!["remove field", "drop table"], schema.rollback());
+        assert_eq!(vec!["remove field", "drop table"], schema.rollback());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut tables = Schema::default();
+        tables.add_migration(
+            || String::from("create table"),
+            || String::from("drop table"),
+        );
+        tables.add_migration(
+            || String::from("create posts"),
+            || String::from("drop posts"),
+        );
+
+        let mut fields = Schema::default();
+        fields.add_migration(add_field, remove_field);
+        fields.add_migration(
+            || String::from("add title"),
+            || String::from("remove title"),
+        );
+
+        let schema = tables.merge(fields);
+
+        assert_eq!(
+            vec!["create table", "create posts", "add field", "add title"],
+            schema.execute()
+        );
+        assert_eq!(
+            vec!["remove title", "remove field", "drop posts", "drop table"],
+            schema.rollback()
+        );
+    }
+
+    #[test]
+    fn test_clear_and_truncate() {
+        let mut schema: Schema = vec![
+            (add_field as FnPtr, remove_field as FnPtr),
+            (add_field, remove_field),
+            (add_field, remove_field),
+        ]
+        .into_iter()
+        .collect();
+
+        schema.truncate(10);
+        assert_eq!(3, schema.len());
+
+        schema.truncate(2);
+        assert_eq!(2, schema.len());
+
+        schema.clear();
+        assert!(schema.is_empty());
+        assert!(schema.execute().is_empty());
+    }
+
+    #[test]
+    fn test_edit_migrations() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(
+            || String::from("create table"),
+            || String::from("drop table"),
+        );
+        schema.add_migration(add_field, remove_field);
+        schema.add_migration(|| String::from("add index"), || String::from("drop index"));
+
+        let (execute, rollback) = schema.remove_migration(1).unwrap();
+        assert_eq!(schema.len(), 2);
+
+        schema.insert_migration(0, execute, rollback).unwrap();
+        assert_eq!(
+            vec!["add field", "create table", "add index"],
+            schema.execute()
+        );
+
+        assert_eq!(
+            schema.insert_migration(4, add_field, remove_field),
+            Err(SchemaError::IndexOutOfRange { index: 4, len: 3 })
+        );
+        assert_eq!(
+            schema.swap(3, 0),
+            Err(SchemaError::IndexOutOfRange { index: 3, len: 3 })
+        );
+    }
+
+    #[test]
+    fn test_collect_schema() {
+        let create: (FnPtr, FnPtr) = (
+            || String::from("create table"),
+            || String::from("drop table"),
+        );
+        let mut schema: Schema = [create].into_iter().collect();
+
+        schema.extend([(add_field as FnPtr, remove_field as FnPtr)]);
+
+        let outputs: Vec<String> = schema.iter().map(|(execute, _)| execute()).collect();
+        assert_eq!(outputs, schema.execute());
+        assert_eq!(vec!["create table", "add field"], schema.execute());
+    }
+
+    #[test]
+    fn test_fallible_schema_stops_at_first_error() {
+        let mut schema = FallibleSchema::new();
+
+        schema.add_migration(|| Ok("create table".into()), || Ok("drop table".into()));
+        schema.add_migration(|| Err("disk full".into()), || Ok("remove field".into()));
+        schema.add_migration(|| Ok("add index".into()), || Err("index in use".into()));
+
+        assert_eq!(
+            schema.execute(),
+            Err(PartialRun {
+                error: String::from("disk full"),
+                completed: vec![String::from("create table")],
+            })
+        );
+        assert_eq!(
+            schema.rollback(),
+            Err(PartialRun {
+                error: String::from("index in use"),
+                completed: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_fallible_schema_success() {
+        let mut schema = FallibleSchema::default();
+
+        schema.add_migration(|| Ok("create table".into()), || Ok("drop table".into()));
+        schema.add_migration(|| Ok("add field".into()), || Ok("remove field".into()));
+
+        assert_eq!(schema.execute().unwrap(), vec!["create table", "add field"]);
+        assert_eq!(
+            schema.rollback().unwrap(),
+            vec!["remove field", "drop table"]
+        );
     }
 }
 
@@ -261,3 +7078,577 @@ mod test_fn_trait_object {
         assert_eq!(vec!["remove field", "drop table"], schema.rollback());
     }
 }
+
+#[cfg(test)]
+mod test_fn_owned {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use crate::patterns::command::fn_owned::Schema;
+
+    #[test]
+    fn test_computed_output() {
+        let counter = Rc::new(Cell::new(0));
+        let mut schema = Schema::default();
+
+        let runs = Rc::clone(&counter);
+        schema.add_migration(
+            move || {
+                runs.set(runs.get() + 1);
+                format!("{:03}_create_table", runs.get())
+            },
+            || String::from("drop table"),
+        );
+
+        assert_eq!(vec!["001_create_table"], schema.execute());
+        assert_eq!(vec!["002_create_table"], schema.execute());
+        assert_eq!(vec!["drop table"], schema.rollback());
+        assert_eq!(counter.get(), 2);
+    }
+}
+
+#[cfg(test)]
+mod test_fn_mut {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use crate::patterns::command::fn_mut::Schema;
+
+    #[test]
+    fn test_command() {
+        let mut schema = Schema::default();
+
+        schema.add_migration(
+            || String::from("create table"),
+            || String::from("drop table"),
+        );
+        schema.add_migration(
+            || String::from("add field"),
+            || String::from("remove field"),
+        );
+
+        assert_eq!(vec!["create table", "add field"], schema.execute());
+        assert_eq!(vec!["remove field", "drop table"], schema.rollback());
+    }
+
+    #[test]
+    fn test_schema_macro() {
+        let mut expected = Schema::default();
+
+        expected.add_migration(|| String::from("x"), || String::from("y"));
+        expected.add_migration(|| String::from("z"), || String::from("w"));
+
+        let mut schema = crate::schema! {
+            up: || "x".into(), down: || "y".into();
+            up: || "z".into(), down: || "w".into()
+        };
+
+        assert_eq!(expected.execute(), schema.execute());
+        assert_eq!(expected.rollback(), schema.rollback());
+    }
+
+    #[test]
+    fn test_captured_counter() {
+        let runs = Rc::new(Cell::new(0));
+        let mut schema = Schema::default();
+
+        let counter = Rc::clone(&runs);
+        schema.add_migration(
+            move || {
+                counter.set(counter.get() + 1);
+                format!("run {}", counter.get())
+            },
+            || String::from("undo"),
+        );
+
+        assert_eq!(vec!["run 1"], schema.execute());
+        assert_eq!(vec!["run 2"], schema.execute());
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[test]
+    fn test_moved_string() {
+        let table = String::from("users");
+        let mut schema = Schema::default();
+
+        schema.add_migration(move || table.clone(), || String::from("drop table"));
+
+        assert_eq!(vec!["users"], schema.execute());
+    }
+}
+
+#[cfg(test)]
+mod test_async_schema {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+
+    use futures::executor::block_on;
+    use futures::future::LocalBoxFuture;
+    use futures::FutureExt;
+
+    use crate::patterns::command::async_schema::{AsyncMigration, AsyncSchema};
+
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    struct Tracked {
+        name: &'static str,
+        yields: usize,
+        in_flight: Arc<AtomicUsize>,
+        high_water: Arc<AtomicUsize>,
+    }
+
+    impl Tracked {
+        async fn run(&self, output: String) -> String {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.high_water.fetch_max(in_flight, Ordering::SeqCst);
+
+            for _ in 0..self.yields {
+                YieldNow(false).await;
+            }
+
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            output
+        }
+    }
+
+    impl AsyncMigration for Tracked {
+        fn execute(&self) -> LocalBoxFuture<'_, String> {
+            self.run(format!("up {}", self.name)).boxed_local()
+        }
+
+        fn rollback(&self) -> LocalBoxFuture<'_, String> {
+            self.run(format!("down {}", self.name)).boxed_local()
+        }
+    }
+
+    fn schema(
+        yields: &[usize],
+        in_flight: &Arc<AtomicUsize>,
+        high_water: &Arc<AtomicUsize>,
+    ) -> AsyncSchema {
+        let names = ["a", "b", "c", "d", "e"];
+        let mut schema = AsyncSchema::default();
+
+        for (name, &yields) in names.iter().zip(yields) {
+            schema.add_migration(Box::new(Tracked {
+                name,
+                yields,
+                in_flight: Arc::clone(in_flight),
+                high_water: Arc::clone(high_water),
+            }));
+        }
+
+        schema
+    }
+
+    #[test]
+    fn test_sequential_order() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let high_water = Arc::new(AtomicUsize::new(0));
+        let schema = schema(&[3, 0, 2], &in_flight, &high_water);
+
+        assert_eq!(vec!["up a", "up b", "up c"], block_on(schema.execute()));
+        assert_eq!(
+            vec!["down c", "down b", "down a"],
+            block_on(schema.rollback())
+        );
+        assert_eq!(high_water.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let high_water = Arc::new(AtomicUsize::new(0));
+        let schema = schema(&[3, 1, 4, 1, 5], &in_flight, &high_water);
+
+        let mut outputs = block_on(schema.execute_concurrent(2));
+        outputs.sort();
+
+        assert_eq!(vec!["up a", "up b", "up c", "up d", "up e"], outputs);
+        assert_eq!(high_water.load(Ordering::SeqCst), 2);
+        assert_eq!(in_flight.load(Ordering::SeqCst), 0);
+    }
+}
+
+#[cfg(test)]
+mod test_fn_once {
+    use crate::patterns::command::fn_once::OneShotSchema;
+
+    fn seed_rows() -> Vec<String> {
+        vec![String::from("alice"), String::from("bob")]
+    }
+
+    #[test]
+    fn test_moves_payload() {
+        let rows = seed_rows();
+
+        let schema = OneShotSchema::new()
+            .migration(
+                || String::from("create table"),
+                || String::from("drop table"),
+            )
+            .migration(move || rows.join(","), || String::from("truncate"));
+
+        assert_eq!(vec!["create table", "alice,bob"], schema.execute());
+    }
+
+    #[test]
+    fn test_rollback_runs_once_in_reverse() {
+        let rows = seed_rows();
+
+        let schema = OneShotSchema::new()
+            .migration(
+                || String::from("create table"),
+                || String::from("drop table"),
+            )
+            .migration(
+                || String::from("seed"),
+                move || format!("delete {}", rows.join(",")),
+            );
+
+        assert_eq!(vec!["delete alice,bob", "drop table"], schema.rollback());
+    }
+}
+
+#[cfg(test)]
+mod test_generic {
+    use std::time::Duration;
+
+    use crate::patterns::command::generic::{Migration, MigrationOutcome, Schema, StringSchema};
+
+    struct InsertRows {
+        inserted: u32,
+    }
+
+    impl Migration<u32> for InsertRows {
+        fn execute(&self) -> u32 {
+            self.inserted
+        }
+
+        fn rollback(&self) -> u32 {
+            self.inserted * 10
+        }
+    }
+
+    #[derive(Clone)]
+    struct CreateTable;
+
+    impl Migration<String> for CreateTable {
+        fn execute(&self) -> String {
+            String::from("create table")
+        }
+
+        fn rollback(&self) -> String {
+            String::from("drop table")
+        }
+    }
+
+    impl Migration<MigrationOutcome> for CreateTable {
+        fn execute(&self) -> MigrationOutcome {
+            MigrationOutcome {
+                duration: Duration::from_millis(5),
+                affected_rows: 0,
+            }
+        }
+
+        fn rollback(&self) -> MigrationOutcome {
+            MigrationOutcome {
+                duration: Duration::from_millis(1),
+                affected_rows: 0,
+            }
+        }
+    }
+
+    #[test]
+    fn test_row_counts() {
+        let mut schema: Schema<u32> = Schema::default();
+
+        schema.add_migration(Box::new(InsertRows { inserted: 1 }));
+        schema.add_migration(Box::new(InsertRows { inserted: 2 }));
+        schema.add_migration(Box::new(InsertRows { inserted: 3 }));
+
+        assert_eq!(vec![1, 2, 3], schema.execute());
+        assert_eq!(6, schema.execute().into_iter().sum::<u32>());
+        assert_eq!(vec![30, 20, 10], schema.rollback());
+    }
+
+    #[test]
+    fn test_output_types() {
+        let mut strings = StringSchema::default();
+        strings.add_migration(Box::new(CreateTable));
+
+        assert_eq!(vec!["create table"], strings.execute());
+
+        let mut outcomes: Schema<MigrationOutcome> = Schema::default();
+        outcomes.add_migration(Box::new(CreateTable));
+
+        assert_eq!(outcomes.execute()[0].duration, Duration::from_millis(5));
+    }
+}
+
+#[cfg(test)]
+mod test_editor {
+    use crate::patterns::command::editor::{Delete, Editor, Insert};
+
+    #[test]
+    fn test_undo_redo() {
+        let mut editor = Editor::new("hello world");
+
+        editor.execute(Box::new(Insert::new(5, ",")));
+        editor.execute(Box::new(Delete::new(6, 6)));
+        assert_eq!("hello,", editor.text());
+
+        assert!(editor.undo());
+        assert_eq!("hello, world", editor.text());
+        assert!(editor.undo());
+        assert_eq!("hello world", editor.text());
+        assert!(!editor.undo());
+
+        assert!(editor.redo());
+        assert!(editor.redo());
+        assert_eq!("hello,", editor.text());
+        assert!(!editor.redo());
+    }
+
+    #[test]
+    fn test_multibyte_delete() {
+        let mut editor = Editor::new("naïve café ☕");
+
+        editor.execute(Box::new(Delete::new(2, 7)));
+        assert_eq!("naé ☕", editor.text());
+
+        editor.execute(Box::new(Insert::new(4, "🍵 ")));
+        assert_eq!("naé 🍵 ☕", editor.text());
+
+        assert!(editor.undo());
+        assert!(editor.undo());
+        assert_eq!("naïve café ☕", editor.text());
+
+        assert!(editor.redo());
+        assert!(editor.redo());
+        assert_eq!("naé 🍵 ☕", editor.text());
+    }
+
+    #[test]
+    fn test_execute_clears_redo() {
+        let mut editor = Editor::new("abc");
+
+        editor.execute(Box::new(Delete::new(0, 1)));
+        assert!(editor.undo());
+
+        editor.execute(Box::new(Insert::new(3, "d")));
+        assert_eq!("abcd", editor.text());
+        assert!(!editor.redo());
+    }
+
+    #[test]
+    fn test_insert_past_end() {
+        let mut editor = Editor::new("ab");
+
+        editor.execute(Box::new(Insert::new(10, "xyz")));
+        assert_eq!("abxyz", editor.text());
+
+        assert!(editor.undo());
+        assert_eq!("ab", editor.text());
+
+        assert!(editor.redo());
+        assert_eq!("abxyz", editor.text());
+    }
+}
+
+#[cfg(test)]
+mod test_ddl {
+    use crate::patterns::command::ddl::{quote_ident, ColumnDef, SqlType};
+    use crate::patterns::command::trait_object::{
+        AddField, CreateTable, Migration, Schema, SchemaState,
+    };
+
+    fn schema() -> Schema {
+        let mut schema = Schema::default();
+
+        schema.add_migration(Box::new(CreateTable::new(
+            "users",
+            vec![
+                ColumnDef::new("id", SqlType::Integer),
+                ColumnDef::new("email", SqlType::Text),
+                ColumnDef::new("score", SqlType::Real),
+            ],
+        )));
+        schema.add_migration(Box::new(AddField::new(
+            "users",
+            ColumnDef::new("active", SqlType::Boolean),
+        )));
+
+        schema
+    }
+
+    #[test]
+    fn test_ddl() {
+        let schema = schema();
+
+        assert_eq!(
+            vec![
+                "CREATE TABLE users (id INTEGER, email TEXT, score REAL);",
+                "ALTER TABLE users ADD COLUMN active BOOLEAN;",
+            ],
+            schema.execute()
+        );
+        assert_eq!(
+            vec!["ALTER TABLE users DROP COLUMN active;", "DROP TABLE users;",],
+            schema.rollback()
+        );
+        assert_eq!(
+            vec!["create_table:users", "add_field:users.active"],
+            schema.iter_names().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_ddl_tracks_tables() {
+        let schema = schema();
+        let mut state = SchemaState::new();
+
+        let report = schema.apply_with_state(&mut state);
+        assert_eq!(vec!["users"], state.tables().collect::<Vec<_>>());
+        assert_eq!(
+            vec!["active", "email", "id", "score"],
+            state.columns("users").collect::<Vec<_>>()
+        );
+
+        schema.revert_with_state(&report, &mut state);
+        assert!(!state.has_table("users"));
+    }
+
+    #[test]
+    fn test_add_field_tracks_columns() {
+        let mut state = SchemaState::new();
+        let field = AddField::new("users", ColumnDef::new("active", SqlType::Boolean));
+
+        field.apply_state(&mut state);
+        assert!(!state.has_table("users"));
+
+        state.create_table("users");
+        field.apply_state(&mut state);
+        assert!(state.has_column("users", "active"));
+
+        field.revert_state(&mut state);
+        assert!(state.has_table("users"));
+        assert!(!state.has_column("users", "active"));
+    }
+
+    #[test]
+    fn test_quoted_identifiers() {
+        assert_eq!("users", quote_ident("users"));
+        assert_eq!("\"order\"", quote_ident("order"));
+        assert_eq!("\"user name\"", quote_ident("user name"));
+        assert_eq!("\"say \"\"hi\"\"\"", quote_ident("say \"hi\""));
+        assert_eq!("\"1st\"", quote_ident("1st"));
+
+        let table = CreateTable::new("Order", vec![ColumnDef::new("select", SqlType::Blob)]);
+
+        assert_eq!("CREATE TABLE \"Order\" (\"select\" BLOB);", table.execute());
+        assert_eq!("DROP TABLE \"Order\";", table.rollback());
+
+        let field = AddField::new("order", ColumnDef::new("a\"b", SqlType::Text));
+
+        assert_eq!(
+            "ALTER TABLE \"order\" ADD COLUMN \"a\"\"b\" TEXT;",
+            field.execute()
+        );
+        assert_eq!(
+            "ALTER TABLE \"order\" DROP COLUMN \"a\"\"b\";",
+            field.rollback()
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_queue {
+    use crate::patterns::command::ddl::{ColumnDef, SqlType};
+    use crate::patterns::command::queue::CommandQueue;
+    use crate::patterns::command::trait_object::{AddField, CreateTable};
+
+    fn create_table() -> CreateTable {
+        CreateTable::new("users", vec![ColumnDef::new("id", SqlType::Integer)])
+    }
+
+    fn add_field() -> AddField {
+        AddField::new("users", ColumnDef::new("email", SqlType::Text))
+    }
+
+    crate::impl_migration!(AddIndex, "add_index", "drop_index");
+
+    #[test]
+    fn test_command_queue() {
+        let mut queue = CommandQueue::new();
+
+        queue.enqueue(Box::new(create_table()));
+        queue.enqueue(Box::new(add_field()));
+        queue.enqueue(Box::new(AddIndex));
+
+        assert_eq!(
+            queue.drain_n(2),
+            vec![
+                "CREATE TABLE users (id INTEGER);",
+                "ALTER TABLE users ADD COLUMN email TEXT;"
+            ]
+        );
+        assert_eq!(queue.len(), 1);
+        assert_eq!(
+            queue.rollback(),
+            vec!["ALTER TABLE users DROP COLUMN email;", "DROP TABLE users;"]
+        );
+        assert!(queue.rollback().is_empty());
+
+        assert_eq!(queue.drain_execute(), vec!["add_index"]);
+        assert!(queue.is_empty());
+
+        queue.clear_history();
+        assert!(queue.rollback().is_empty());
+    }
+
+    #[test]
+    fn test_tick() {
+        let mut queue = CommandQueue::new();
+
+        queue.enqueue(Box::new(create_table()));
+        queue.enqueue(Box::new(add_field()));
+        queue.enqueue(Box::new(AddIndex));
+
+        assert_eq!(
+            queue.tick().as_deref(),
+            Some("CREATE TABLE users (id INTEGER);")
+        );
+        assert_eq!(
+            queue.tick().as_deref(),
+            Some("ALTER TABLE users ADD COLUMN email TEXT;")
+        );
+        assert_eq!(queue.tick().as_deref(), Some("add_index"));
+        assert_eq!(queue.tick(), None);
+        assert_eq!(
+            queue.rollback(),
+            vec![
+                "drop_index",
+                "ALTER TABLE users DROP COLUMN email;",
+                "DROP TABLE users;"
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+// Leaves and containers share one interface, so callers can ask any node for its size
+// without caring whether it is a single file or a whole tree.
+pub trait Node {
+    fn size(&self) -> u64;
+    fn name(&self) -> &str;
+}
+
+pub struct File {
+    name: String,
+    size: u64,
+}
+
+impl File {
+    pub fn new(name: &str, size: u64) -> Self {
+        File {
+            name: String::from(name),
+            size,
+        }
+    }
+}
+
+impl Node for File {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+pub struct Directory {
+    name: String,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Directory {
+    pub fn new(name: &str) -> Self {
+        Directory {
+            name: String::from(name),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, node: Box<dyn Node>) {
+        self.children.push(node);
+    }
+}
+
+impl Node for Directory {
+    fn size(&self) -> u64 {
+        self.children.iter().map(|child| child.size()).sum()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::patterns::composite::{Directory, File, Node};
+
+    #[test]
+    fn test_composite() {
+        let mut src = Directory::new("src");
+        src.add(Box::new(File::new("main.rs", 100)));
+        src.add(Box::new(File::new("lib.rs", 50)));
+
+        let mut patterns = Directory::new("patterns");
+        patterns.add(Box::new(File::new("command.rs", 400)));
+        src.add(Box::new(patterns));
+
+        let mut root = Directory::new("crate");
+        root.add(Box::new(File::new("Cargo.toml", 25)));
+        root.add(Box::new(src));
+
+        assert_eq!(root.name(), "crate");
+        assert_eq!(root.size(), 575);
+        assert_eq!(Directory::new("empty").size(), 0);
+    }
+}
@@ -0,0 +1,61 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+// A service locator: instead of threading every dependency through constructors as
+// `strategy_di` does, services are registered once by type and looked up where needed.
+// The price is that a missing registration is only noticed at runtime.
+pub struct Container {
+    services: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl Container {
+    pub fn new() -> Self {
+        Container {
+            services: HashMap::new(),
+        }
+    }
+
+    /// Registers `value` as the service for `T`, replacing any earlier one.
+    pub fn register<T: 'static>(&mut self, value: T) {
+        self.services.insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    pub fn resolve<T: 'static>(&self) -> Option<&T> {
+        self.services
+            .get(&TypeId::of::<T>())
+            .and_then(|service| service.downcast_ref::<T>())
+    }
+}
+
+impl Default for Container {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::patterns::di_container::Container;
+    use crate::patterns::strategy_di::{Database, MySQLDatabase, PostgresDatabase};
+
+    #[test]
+    fn test_resolve() {
+        let mut container = Container::new();
+
+        container.register(MySQLDatabase);
+
+        let db = container.resolve::<MySQLDatabase>().unwrap();
+        assert_eq!(db.query("SELECT 1;"), "MySQL: SELECT 1;");
+        assert!(container.resolve::<PostgresDatabase>().is_none());
+    }
+
+    #[test]
+    fn test_register_replaces() {
+        let mut container = Container::default();
+
+        container.register(String::from("first"));
+        container.register(String::from("second"));
+
+        assert_eq!(container.resolve::<String>().unwrap(), "second");
+    }
+}
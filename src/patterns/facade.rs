@@ -0,0 +1,64 @@
+use crate::patterns::command::ddl::{ColumnDef, SqlType};
+use crate::patterns::command::trait_object::{AddField, CreateTable, Schema};
+use crate::patterns::strategy_di::{
+    AdditionStrategy, Context, DataService, MySQLDatabase, SqlQuery, SqlQueryError,
+};
+
+// Callers get one entry point and never see how the migration schema and the
+// strategy/database wiring are put together.
+pub struct AppFacade {
+    schema: Schema,
+    context: Context<AdditionStrategy, MySQLDatabase>,
+}
+
+impl AppFacade {
+    pub fn new() -> Self {
+        let mut schema = Schema::default();
+        schema.add_migration(Box::new(CreateTable::new(
+            "users",
+            vec![ColumnDef::new("id", SqlType::Integer)],
+        )));
+        schema.add_migration(Box::new(AddField::new(
+            "users",
+            ColumnDef::new("email", SqlType::Text),
+        )));
+
+        let context = Context::new(AdditionStrategy, DataService::new(MySQLDatabase));
+
+        AppFacade { schema, context }
+    }
+
+    /// Fails without migrating anything if `sql` isn't a valid query.
+    pub fn run_migration_and_query(&self, sql: &str) -> Result<String, SqlQueryError> {
+        let query = SqlQuery::new(sql)?;
+        let migrations = self.schema.execute().join(", ");
+        let result = self.context.query(query);
+
+        Ok(format!("migrated: {}; queried: {}", migrations, result))
+    }
+}
+
+impl Default for AppFacade {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::patterns::facade::AppFacade;
+
+    #[test]
+    fn test_facade() {
+        let facade = AppFacade::new();
+
+        let output = facade
+            .run_migration_and_query("SELECT * FROM users;")
+            .unwrap();
+
+        assert!(output.contains(
+            "CREATE TABLE users (id INTEGER);, ALTER TABLE users ADD COLUMN email TEXT;"
+        ));
+        assert!(output.contains("MySQL: SELECT * FROM users;"));
+    }
+}
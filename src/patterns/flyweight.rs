@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// The intrinsic state of a character: shared between every occurrence of it.
+pub struct Glyph {
+    symbol: char,
+}
+
+impl Glyph {
+    pub fn symbol(&self) -> char {
+        self.symbol
+    }
+}
+
+#[derive(Default)]
+pub struct GlyphFactory {
+    glyphs: HashMap<char, Rc<Glyph>>,
+}
+
+impl GlyphFactory {
+    pub fn new() -> Self {
+        GlyphFactory {
+            glyphs: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, symbol: char) -> Rc<Glyph> {
+        Rc::clone(
+            self.glyphs
+                .entry(symbol)
+                .or_insert_with(|| Rc::new(Glyph { symbol })),
+        )
+    }
+
+    pub fn distinct_count(&self) -> usize {
+        self.glyphs.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use crate::patterns::flyweight::GlyphFactory;
+
+    #[test]
+    fn test_flyweight() {
+        let mut factory = GlyphFactory::new();
+
+        let first_a = factory.get('a');
+        let b = factory.get('b');
+        let second_a = factory.get('a');
+
+        assert_eq!(factory.distinct_count(), 2);
+        assert!(Rc::ptr_eq(&first_a, &second_a));
+        assert!(!Rc::ptr_eq(&first_a, &b));
+        assert_eq!(second_a.symbol(), 'a');
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+// Each variant is a grammar rule and `interpret` evaluates the tree recursively:
+//   expr := var | expr AND expr | expr OR expr | NOT expr
+pub enum Expr {
+    Var(String),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+impl Expr {
+    pub fn var(name: &str) -> Self {
+        Expr::Var(String::from(name))
+    }
+
+    pub fn and(lhs: Expr, rhs: Expr) -> Self {
+        Expr::And(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn or(lhs: Expr, rhs: Expr) -> Self {
+        Expr::Or(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn negate(expr: Expr) -> Self {
+        Expr::Not(Box::new(expr))
+    }
+
+    /// Unbound variables evaluate to `false`.
+    pub fn interpret(&self, ctx: &HashMap<String, bool>) -> bool {
+        match self {
+            Expr::Var(name) => ctx.get(name).copied().unwrap_or(false),
+            Expr::And(lhs, rhs) => lhs.interpret(ctx) && rhs.interpret(ctx),
+            Expr::Or(lhs, rhs) => lhs.interpret(ctx) || rhs.interpret(ctx),
+            Expr::Not(expr) => !expr.interpret(ctx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::patterns::interpreter::Expr;
+
+    fn ctx(vars: &[(&str, bool)]) -> HashMap<String, bool> {
+        vars.iter()
+            .map(|&(name, value)| (String::from(name), value))
+            .collect()
+    }
+
+    #[test]
+    fn test_interpreter() {
+        let nand = Expr::negate(Expr::and(Expr::var("a"), Expr::var("b")));
+
+        assert!(!nand.interpret(&ctx(&[("a", true), ("b", true)])));
+        assert!(nand.interpret(&ctx(&[("a", true), ("b", false)])));
+        assert!(nand.interpret(&ctx(&[("a", false), ("b", false)])));
+
+        let either = Expr::or(Expr::var("a"), Expr::var("b"));
+
+        assert!(either.interpret(&ctx(&[("a", false), ("b", true)])));
+    }
+
+    #[test]
+    fn test_missing_variable_is_false() {
+        let nand = Expr::negate(Expr::and(Expr::var("a"), Expr::var("b")));
+
+        assert!(!Expr::var("missing").interpret(&ctx(&[])));
+        assert!(nand.interpret(&ctx(&[("a", true)])));
+    }
+}
@@ -0,0 +1,75 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+// The room owns its participants while each user only holds a `Weak` back to the room,
+// so the mediator and its colleagues never keep each other alive.
+pub struct ChatRoom {
+    users: RefCell<Vec<Rc<User>>>,
+}
+
+impl ChatRoom {
+    pub fn new() -> Rc<Self> {
+        Rc::new(ChatRoom {
+            users: RefCell::new(Vec::new()),
+        })
+    }
+
+    pub fn join(room: &Rc<ChatRoom>, name: &str) -> Rc<User> {
+        let user = Rc::new(User {
+            name: String::from(name),
+            room: Rc::downgrade(room),
+            received: RefCell::new(Vec::new()),
+        });
+
+        room.users.borrow_mut().push(Rc::clone(&user));
+
+        user
+    }
+
+    fn broadcast(&self, from: &str, message: &str) {
+        for user in self.users.borrow().iter().filter(|user| user.name != from) {
+            user.receive(&format!("{}: {}", from, message));
+        }
+    }
+}
+
+pub struct User {
+    name: String,
+    room: Weak<ChatRoom>,
+    received: RefCell<Vec<String>>,
+}
+
+impl User {
+    pub fn send(&self, message: &str) {
+        if let Some(room) = self.room.upgrade() {
+            room.broadcast(&self.name, message);
+        }
+    }
+
+    pub fn received(&self) -> Vec<String> {
+        self.received.borrow().clone()
+    }
+
+    fn receive(&self, message: &str) {
+        self.received.borrow_mut().push(String::from(message));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::patterns::mediator::ChatRoom;
+
+    #[test]
+    fn test_mediator() {
+        let room = ChatRoom::new();
+        let alice = ChatRoom::join(&room, "alice");
+        let bob = ChatRoom::join(&room, "bob");
+        let carol = ChatRoom::join(&room, "carol");
+
+        alice.send("hello");
+
+        assert!(alice.received().is_empty());
+        assert_eq!(bob.received(), vec!["alice: hello"]);
+        assert_eq!(carol.received(), vec!["alice: hello"]);
+    }
+}
@@ -1,4 +1,19 @@
+pub mod abstract_factory;
+pub mod bridge;
+pub mod cache;
 pub mod command;
+pub mod composite;
+pub mod di_container;
+pub mod facade;
+pub mod flyweight;
+pub mod interpreter;
+pub mod mediator;
 pub mod newtype;
+pub mod null_object;
+pub mod prototype;
+pub mod proxy;
 pub mod raii_guard;
+pub mod raii_lock;
+pub mod singleton;
 pub mod strategy_di;
+pub mod template_method;
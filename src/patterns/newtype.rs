@@ -1,6 +1,8 @@
 use std::fmt::Display;
+use std::ops::{Add, Deref};
 
-pub struct Password(String);
+// The plain text is only ever written: keeping it out of reach is the point of the newtype.
+pub struct Password(#[allow(dead_code)] String);
 
 impl Display for Password {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -8,9 +10,129 @@ impl Display for Password {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmptyStringError;
+
+impl Display for EmptyStringError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "string is empty or whitespace-only")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonEmptyString(String);
+
+impl NonEmptyString {
+    pub fn new(s: String) -> Result<Self, EmptyStringError> {
+        if s.trim().is_empty() {
+            Err(EmptyStringError)
+        } else {
+            Ok(NonEmptyString(s))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for NonEmptyString {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// The range is part of the type, so `Bounded<0, 100>` and `Bounded<0, 10>` can't be mixed up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bounded<const MIN: i32, const MAX: i32>(i32);
+
+impl<const MIN: i32, const MAX: i32> Bounded<MIN, MAX> {
+    pub fn new(value: i32) -> Option<Self> {
+        if (MIN..=MAX).contains(&value) {
+            Some(Bounded(value))
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self) -> i32 {
+        self.0
+    }
+}
+
+// Always within 0-100: out-of-range inputs are clamped rather than rejected, and sums
+// saturate at 100.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Percentage(f64);
+
+impl Percentage {
+    /// NaN has no sensible percentage and becomes 0.
+    pub fn new(value: f64) -> Self {
+        if value.is_nan() {
+            Percentage(0.0)
+        } else {
+            Percentage(value.clamp(0.0, 100.0))
+        }
+    }
+
+    pub fn get(&self) -> f64 {
+        self.0
+    }
+}
+
+impl Display for Percentage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+impl Add for Percentage {
+    type Output = Percentage;
+
+    fn add(self, other: Percentage) -> Percentage {
+        Percentage::new(self.0 + other.0)
+    }
+}
+
+// A value that parsed successfully but may have raised non-fatal warnings along the way,
+// which travel with it through `map` instead of being lost or logged out of band.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parsed<T>(T, Vec<String>);
+
+impl<T> Parsed<T> {
+    pub fn new(value: T) -> Self {
+        Parsed(value, Vec::new())
+    }
+
+    pub fn value(&self) -> &T {
+        &self.0
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.1
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Parsed<U> {
+        Parsed(f(self.0), self.1)
+    }
+
+    pub fn add_warning(mut self, warning: &str) -> Self {
+        self.1.push(warning.to_string());
+        self
+    }
+
+    pub fn into_inner(self) -> (T, Vec<String>) {
+        (self.0, self.1)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::patterns::newtype::Password;
+    use crate::patterns::newtype::{
+        Bounded, EmptyStringError, NonEmptyString, Parsed, Password, Percentage,
+    };
 
     #[test]
     fn test_password() {
@@ -19,4 +141,79 @@ mod test {
 
         assert_eq!(format!("{secured_password}"), "*".repeat(16));
     }
+
+    #[test]
+    fn test_non_empty_string() {
+        let name = NonEmptyString::new(String::from("Alice")).unwrap();
+
+        assert_eq!(name.as_str(), "Alice");
+        assert_eq!(name.len(), 5);
+        assert!(name.starts_with("Al"));
+    }
+
+    #[test]
+    fn test_empty_string_rejected() {
+        assert_eq!(NonEmptyString::new(String::new()), Err(EmptyStringError));
+        assert_eq!(
+            NonEmptyString::new(String::from("   ")),
+            Err(EmptyStringError)
+        );
+    }
+
+    type Die = Bounded<1, 6>;
+
+    #[test]
+    fn test_bounded() {
+        assert_eq!(Die::new(1).map(|die| die.get()), Some(1));
+        assert_eq!(Die::new(6).map(|die| die.get()), Some(6));
+        assert_eq!(Die::new(0), None);
+        assert_eq!(Die::new(7), None);
+    }
+
+    #[test]
+    fn test_parsed() {
+        let parsed = Parsed::new("0042".trim_start_matches('0'))
+            .add_warning("leading zeros ignored")
+            .map(|digits| digits.parse::<i32>().unwrap())
+            .add_warning("value exceeds recommended maximum of 40")
+            .map(|value| value * 2);
+
+        assert_eq!(*parsed.value(), 84);
+        assert_eq!(
+            parsed.into_inner(),
+            (
+                84,
+                vec![
+                    String::from("leading zeros ignored"),
+                    String::from("value exceeds recommended maximum of 40")
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_percentage_clamps() {
+        assert_eq!(Percentage::new(150.0).get(), 100.0);
+        assert_eq!(Percentage::new(-5.0).get(), 0.0);
+        assert_eq!(Percentage::new(f64::NAN).get(), 0.0);
+        assert_eq!(Percentage::new(42.5).get(), 42.5);
+    }
+
+    #[test]
+    fn test_percentage_display() {
+        assert_eq!(Percentage::new(60.0).to_string(), "60%");
+        assert_eq!(Percentage::new(12.5).to_string(), "12.5%");
+    }
+
+    #[test]
+    fn test_percentage_saturating_add() {
+        assert_eq!(
+            Percentage::new(60.0) + Percentage::new(50.0),
+            Percentage::new(100.0)
+        );
+        assert_eq!(
+            (Percentage::new(20.0) + Percentage::new(30.0)).to_string(),
+            "50%"
+        );
+    }
 }
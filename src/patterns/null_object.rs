@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+
+pub trait Logger {
+    fn log(&self, message: &str);
+}
+
+/// A logger that does nothing. Passing it where logging isn't wanted keeps callers free of
+/// `if let Some(logger)` checks, which an `Option<Box<dyn Logger>>` would force on every use.
+pub struct NullLogger;
+
+impl Logger for NullLogger {
+    fn log(&self, _message: &str) {}
+}
+
+pub struct StdoutLogger;
+
+impl Logger for StdoutLogger {
+    fn log(&self, message: &str) {
+        println!("{}", message);
+    }
+}
+
+#[derive(Default)]
+pub struct RecordingLogger {
+    messages: RefCell<Vec<String>>,
+}
+
+impl RecordingLogger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.messages.borrow().clone()
+    }
+}
+
+impl Logger for RecordingLogger {
+    fn log(&self, message: &str) {
+        self.messages.borrow_mut().push(message.to_string());
+    }
+}
+
+/// Always has a logger, so it just logs.
+pub fn process_order(quantity: u32, unit_price: u32, logger: &dyn Logger) -> u32 {
+    logger.log(&format!("pricing {} items at {}", quantity, unit_price));
+    let total = quantity * unit_price;
+    logger.log(&format!("total {}", total));
+
+    total
+}
+
+/// The same, without a null object: every log call has to check for a logger first.
+pub fn process_order_optional(
+    quantity: u32,
+    unit_price: u32,
+    logger: Option<Box<dyn Logger>>,
+) -> u32 {
+    if let Some(logger) = &logger {
+        logger.log(&format!("pricing {} items at {}", quantity, unit_price));
+    }
+    let total = quantity * unit_price;
+    if let Some(logger) = &logger {
+        logger.log(&format!("total {}", total));
+    }
+
+    total
+}
+
+#[cfg(test)]
+mod test {
+    use crate::patterns::null_object::{
+        process_order, process_order_optional, NullLogger, RecordingLogger,
+    };
+
+    #[test]
+    fn test_null_logger() {
+        assert_eq!(process_order(3, 4, &NullLogger), 12);
+        assert_eq!(process_order_optional(3, 4, None), 12);
+    }
+
+    #[test]
+    fn test_recording_logger() {
+        let logger = RecordingLogger::new();
+
+        assert_eq!(process_order(3, 4, &logger), 12);
+        assert_eq!(logger.messages(), vec!["pricing 3 items at 4", "total 12"]);
+    }
+}
@@ -0,0 +1,93 @@
+use std::f64::consts::PI;
+
+// `#[derive(Clone)]` only works on concrete types: `Clone` is not object safe, so a
+// `Box<dyn Shape>` can't be cloned through it. Each shape instead knows how to copy itself
+// into a fresh box, and `Clone` for the box is built on top of that.
+pub trait Shape {
+    fn clone_box(&self) -> Box<dyn Shape>;
+    fn area(&self) -> f64;
+}
+
+impl Clone for Box<dyn Shape> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Clone)]
+pub struct Circle {
+    radius: f64,
+}
+
+impl Circle {
+    pub fn new(radius: f64) -> Self {
+        Circle { radius }
+    }
+
+    pub fn set_radius(&mut self, radius: f64) {
+        self.radius = radius;
+    }
+}
+
+impl Shape for Circle {
+    fn clone_box(&self) -> Box<dyn Shape> {
+        Box::new(self.clone())
+    }
+
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+}
+
+#[derive(Clone)]
+pub struct Rectangle {
+    width: f64,
+    height: f64,
+}
+
+impl Rectangle {
+    pub fn new(width: f64, height: f64) -> Self {
+        Rectangle { width, height }
+    }
+}
+
+impl Shape for Rectangle {
+    fn clone_box(&self) -> Box<dyn Shape> {
+        Box::new(self.clone())
+    }
+
+    fn area(&self) -> f64 {
+        self.width * self.height
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use crate::patterns::prototype::{Circle, Rectangle, Shape};
+
+    #[test]
+    fn test_prototype() {
+        let mut original = Box::new(Circle::new(1.0));
+        let copy: Box<dyn Shape> = original.clone_box();
+
+        original.set_radius(2.0);
+
+        assert_eq!(original.area(), 4.0 * PI);
+        assert_eq!(copy.area(), PI);
+    }
+
+    #[test]
+    fn test_clone_trait_object() {
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Circle::new(1.0)),
+            Box::new(Rectangle::new(2.0, 3.0)),
+        ];
+        let copies = shapes.clone();
+
+        let areas: Vec<f64> = copies.iter().map(|shape| shape.area()).collect();
+
+        assert_eq!(areas, vec![PI, 6.0]);
+    }
+}
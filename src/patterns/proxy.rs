@@ -0,0 +1,73 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+pub trait Resource {
+    fn request(&self) -> String;
+}
+
+pub struct ExpensiveResource {
+    data: String,
+}
+
+impl ExpensiveResource {
+    /// Stands in for something costly to build (loading a file, opening a connection, ...).
+    /// `constructions` is bumped every time one is created so callers can observe it.
+    pub fn new(constructions: &Cell<usize>) -> Self {
+        constructions.set(constructions.get() + 1);
+
+        ExpensiveResource {
+            data: String::from("expensive data"),
+        }
+    }
+}
+
+impl Resource for ExpensiveResource {
+    fn request(&self) -> String {
+        format!("ExpensiveResource: {}", self.data)
+    }
+}
+
+pub struct LazyProxy {
+    constructions: Rc<Cell<usize>>,
+    resource: RefCell<Option<ExpensiveResource>>,
+}
+
+impl LazyProxy {
+    pub fn new(constructions: Rc<Cell<usize>>) -> Self {
+        LazyProxy {
+            constructions,
+            resource: RefCell::new(None),
+        }
+    }
+}
+
+impl Resource for LazyProxy {
+    fn request(&self) -> String {
+        self.resource
+            .borrow_mut()
+            .get_or_insert_with(|| ExpensiveResource::new(&self.constructions))
+            .request()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use crate::patterns::proxy::{LazyProxy, Resource};
+
+    #[test]
+    fn test_lazy_proxy() {
+        let constructions = Rc::new(Cell::new(0));
+        let proxy = LazyProxy::new(Rc::clone(&constructions));
+
+        assert_eq!(constructions.get(), 0);
+
+        assert_eq!(proxy.request(), "ExpensiveResource: expensive data");
+        assert_eq!(constructions.get(), 1);
+
+        assert_eq!(proxy.request(), "ExpensiveResource: expensive data");
+        assert_eq!(constructions.get(), 1);
+    }
+}
@@ -1,17 +1,293 @@
+use std::cell::Cell;
+use std::fmt;
 use std::io;
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 
-struct NetworkConnection {
+const DEFAULT_HOST: &str = "localhost";
+const DEFAULT_PORT: u16 = 8080;
+
+// Wait before the first retry of a refused connection, doubled for each one after.
+const RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+// Stands in for a host that refuses connections, so the failure path can be exercised.
+const UNREACHABLE_HOST: &str = "unreachable.example";
+
+/// Where connections report what they do. Defaults to `StdoutLogger`.
+pub trait Logger: Send + Sync {
+    fn log(&self, msg: &str);
+}
+
+pub struct StdoutLogger;
+
+impl Logger for StdoutLogger {
+    fn log(&self, msg: &str) {
+        println!("{}", msg);
+    }
+}
+
+/// Keeps every message, for asserting on them.
+#[derive(Default)]
+pub struct VecLogger {
+    messages: Mutex<Vec<String>>,
+}
+
+impl VecLogger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.messages
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl Logger for VecLogger {
+    fn log(&self, msg: &str) {
+        self.messages
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(msg.to_string());
+    }
+}
+
+fn stdout_logger() -> Arc<dyn Logger> {
+    Arc::new(StdoutLogger)
+}
+
+/// Something that has to be given back once it's no longer needed.
+pub trait Resource: Sized {
+    fn acquire() -> io::Result<Self>;
+
+    fn release(&mut self);
+}
+
+/// Holds a resource and releases it exactly once: on `release`, on `replace` or on drop,
+/// whichever comes first.
+pub struct ResourceGuard<R: Resource> {
+    resource: Option<R>,
+}
+
+impl<R: Resource> ResourceGuard<R> {
+    /// Takes over a resource that has already been acquired.
+    pub fn new(resource: R) -> Self {
+        ResourceGuard {
+            resource: Some(resource),
+        }
+    }
+
+    pub fn acquire() -> io::Result<Self> {
+        R::acquire().map(Self::new)
+    }
+
+    /// `None` once the resource has been released.
+    pub fn get(&self) -> Option<&R> {
+        self.resource.as_ref()
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut R> {
+        self.resource.as_mut()
+    }
+
+    /// Releases the resource now. Returns whether there was anything left to release.
+    pub fn release(&mut self) -> bool {
+        match self.resource.take() {
+            Some(mut resource) => {
+                resource.release();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Releases the current resource, if any, and holds `resource` instead.
+    pub fn replace(&mut self, resource: R) {
+        self.release();
+        self.resource = Some(resource);
+    }
+}
+
+impl<R: Resource> Drop for ResourceGuard<R> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// A second resource, to show `ResourceGuard` isn't tied to connections. It counts its
+/// releases so callers can check there is only ever one.
+pub struct FakeFileHandle {
+    releases: Rc<Cell<u32>>,
+}
+
+impl FakeFileHandle {
+    pub fn releases(&self) -> Rc<Cell<u32>> {
+        Rc::clone(&self.releases)
+    }
+}
+
+impl Resource for FakeFileHandle {
+    fn acquire() -> io::Result<Self> {
+        Ok(FakeFileHandle {
+            releases: Rc::new(Cell::new(0)),
+        })
+    }
+
+    fn release(&mut self) {
+        self.releases.set(self.releases.get() + 1);
+    }
+}
+
+/// Counts resources that were acquired but never dropped. A leak is anything still live once
+/// every owner should be gone, e.g. after `mem::forget`.
+pub struct LeakDetector {
+    live: AtomicUsize,
+}
+
+impl LeakDetector {
+    pub const fn new() -> Self {
+        LeakDetector {
+            live: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records an acquisition. The resource keeps the token and the drop gives it back.
+    pub fn track(&'static self) -> LeakToken {
+        self.live.fetch_add(1, Ordering::SeqCst);
+        LeakToken { detector: self }
+    }
+
+    pub fn live(&self) -> usize {
+        self.live.load(Ordering::SeqCst)
+    }
+
+    #[cfg(test)]
+    pub fn assert_no_leaks(&self) {
+        let live = self.live();
+        assert!(live == 0, "{} resources leaked", live);
+    }
+}
+
+impl Default for LeakDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct LeakToken {
+    detector: &'static LeakDetector,
+}
+
+impl Drop for LeakToken {
+    fn drop(&mut self) {
+        self.detector.live.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Every connection is tracked here. Tests use a detector of their own through
+// `connect_tracked`, as other tests hold connections concurrently.
+static NETWORK_CONNECTIONS: LeakDetector = LeakDetector::new();
+
+pub struct NetworkConnection {
+    endpoint: String,
     connected: bool,
+    traffic: Traffic,
+    logger: Arc<dyn Logger>,
+    _tracked: LeakToken,
+}
+
+#[derive(Clone)]
+struct Traffic {
+    opened_at: Instant,
+    bytes_sent: Cell<usize>,
+    messages_sent: Cell<usize>,
+}
+
+impl Traffic {
+    fn new() -> Self {
+        Traffic {
+            opened_at: Instant::now(),
+            bytes_sent: Cell::new(0),
+            messages_sent: Cell::new(0),
+        }
+    }
+
+    fn stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            bytes_sent: self.bytes_sent.get(),
+            messages_sent: self.messages_sent.get(),
+            connection_duration: self.opened_at.elapsed(),
+        }
+    }
 }
 
 impl NetworkConnection {
-    fn connect() -> Self {
-        NetworkConnection { connected: true }
+    fn connect(endpoint: &str) -> io::Result<Self> {
+        Self::open(endpoint, &NETWORK_CONNECTIONS, stdout_logger())
+    }
+
+    #[cfg(test)]
+    fn connect_tracked(endpoint: &str, detector: &'static LeakDetector) -> io::Result<Self> {
+        Self::open(endpoint, detector, stdout_logger())
+    }
+
+    fn open(
+        endpoint: &str,
+        detector: &'static LeakDetector,
+        logger: Arc<dyn Logger>,
+    ) -> io::Result<Self> {
+        let host = endpoint.split(':').next().unwrap_or_default();
+
+        if host == UNREACHABLE_HOST {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("connection to {} refused", endpoint),
+            ));
+        }
+
+        logger.log(&format!("Connected to {}", endpoint));
+
+        Ok(NetworkConnection {
+            endpoint: endpoint.to_string(),
+            connected: true,
+            traffic: Traffic::new(),
+            logger,
+            _tracked: detector.track(),
+        })
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Successful sends only. Bytes are the UTF-8 length of each payload.
+    pub fn stats(&self) -> ConnectionStats {
+        self.traffic.stats()
     }
 
-    fn send_data(&self, data: &str) -> io::Result<()> {
+    pub fn send_data(&self, data: &str) -> io::Result<()> {
         if self.connected {
-            println!("Sending data over the network: {}", data);
+            self.logger.log(&format!(
+                "Sending data over the network to {}: {}",
+                self.endpoint, data
+            ));
+
+            let traffic = &self.traffic;
+            traffic
+                .bytes_sent
+                .set(traffic.bytes_sent.get() + data.len());
+            traffic.messages_sent.set(traffic.messages_sent.get() + 1);
             Ok(())
         } else {
             Err(io::Error::new(
@@ -20,57 +296,1683 @@ impl NetworkConnection {
             ))
         }
     }
+}
+
+impl Resource for NetworkConnection {
+    fn acquire() -> io::Result<Self> {
+        Self::connect(&format!("{}:{}", DEFAULT_HOST, DEFAULT_PORT))
+    }
 
-    fn close(&mut self) {
-        println!("Closing network connection");
+    fn release(&mut self) {
         self.connected = false;
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectOptionsError {
+    EmptyHost,
+    ZeroPort,
+}
+
+impl fmt::Display for ConnectOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectOptionsError::EmptyHost => write!(f, "host must not be empty"),
+            ConnectOptionsError::ZeroPort => write!(f, "port must be between 1 and 65535"),
+        }
+    }
+}
+
+impl std::error::Error for ConnectOptionsError {}
+
+/// Where and how `ConnectionGuard::with_options` connects. Only built through
+/// `ConnectOptionsBuilder`, so an instance is always valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectOptions {
+    host: String,
+    port: u16,
+    timeout: Duration,
+    retries: u32,
+}
+
+impl ConnectOptions {
+    pub fn builder() -> ConnectOptionsBuilder {
+        ConnectOptionsBuilder::new()
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// How long `ConnectionGuard::with_options` keeps retrying a refused connection.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    pub fn endpoint(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// The same endpoint `ConnectionGuard::new` uses, with no retries.
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        ConnectOptions {
+            host: DEFAULT_HOST.to_string(),
+            port: DEFAULT_PORT,
+            timeout: Duration::from_secs(30),
+            retries: 0,
+        }
+    }
+}
+
+pub struct ConnectOptionsBuilder {
+    options: ConnectOptions,
+}
+
+impl ConnectOptionsBuilder {
+    pub fn new() -> Self {
+        ConnectOptionsBuilder {
+            options: ConnectOptions::default(),
+        }
+    }
+
+    pub fn host(mut self, host: &str) -> Self {
+        self.options.host = host.to_string();
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.options.port = port;
+        self
+    }
+
+    /// Time after which no more retries are made, counted from the first attempt. Retries
+    /// back off from 10ms, doubling each time, and never wait past the timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    /// Extra attempts made after a refused connection.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.options.retries = retries;
+        self
+    }
+
+    pub fn build(self) -> Result<ConnectOptions, ConnectOptionsError> {
+        if self.options.host.trim().is_empty() {
+            return Err(ConnectOptionsError::EmptyHost);
+        }
+        if self.options.port == 0 {
+            return Err(ConnectOptionsError::ZeroPort);
+        }
+
+        Ok(self.options)
+    }
+}
+
+impl Default for ConnectOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static ACTIVE_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+type EventHook = Box<dyn Fn(&str)>;
+type CloseHook = Box<dyn FnOnce(ConnectionStats)>;
+
+/// Traffic through a connection. A `ConnectionGuard` carries it across reconnects, so it
+/// covers the guard's whole life.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionStats {
+    pub bytes_sent: usize,
+    pub messages_sent: usize,
+    pub connection_duration: Duration,
+}
+
+/// A `ResourceGuard` over a network connection. `close` releases the connection through the
+/// `ResourceGuard`, so the guard keeps what `reconnect` needs to open a new one: the endpoint,
+/// the logger and the traffic so far. Once a send fails the guard is poisoned and refuses
+/// further sends until `reconnect`.
 pub struct ConnectionGuard {
-    network: Option<NetworkConnection>,
+    network: ResourceGuard<NetworkConnection>,
+    endpoint: String,
+    logger: Arc<dyn Logger>,
+    poisoned: Cell<bool>,
+    // Taken from the connection when it is released, so stats survive `close` and carry over
+    // to the connection `reconnect` opens.
+    traffic: Traffic,
+    releases: Rc<Cell<u32>>,
+    on_event: Option<EventHook>,
+    on_close: Option<CloseHook>,
 }
 
 impl ConnectionGuard {
+    /// Connects to the default endpoint, which always accepts.
     pub fn new() -> Self {
-        ConnectionGuard {
-            network: Some(NetworkConnection::connect()),
+        Self::with_options(&ConnectOptions::default())
+            .expect("the default endpoint accepts connections")
+    }
+
+    /// Connects to `options.endpoint()`, trying up to `options.retries()` more times if the
+    /// connection is refused, backing off between attempts. Once `options.timeout()` has
+    /// passed it gives up early, with `ErrorKind::TimedOut`.
+    pub fn with_options(options: &ConnectOptions) -> io::Result<Self> {
+        Self::with_options_and_logger(options, stdout_logger())
+    }
+
+    /// Like `with_options`, reporting to `logger` instead of stdout.
+    pub fn with_options_and_logger(
+        options: &ConnectOptions,
+        logger: Arc<dyn Logger>,
+    ) -> io::Result<Self> {
+        let endpoint = options.endpoint();
+        let started = Instant::now();
+        let mut attempts_left = options.retries();
+        let mut backoff = RETRY_BACKOFF;
+
+        loop {
+            match Self::connect_with_logger(&endpoint, Arc::clone(&logger)) {
+                Err(err) if attempts_left > 0 => {
+                    let remaining = options.timeout().saturating_sub(started.elapsed());
+
+                    if remaining.is_zero() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            format!(
+                                "no connection to {} within {:?}: {}",
+                                endpoint,
+                                options.timeout(),
+                                err
+                            ),
+                        ));
+                    }
+
+                    logger.log(&format!("Retrying {} after: {}", endpoint, err));
+                    thread::sleep(backoff.min(remaining));
+                    backoff = backoff.saturating_mul(2);
+                    attempts_left -= 1;
+                }
+                result => return result,
+            }
         }
     }
 
+    /// Acquiring the resource can fail, in which case there is no guard and so nothing to
+    /// drop: cleanup only ever runs for connections that were actually opened.
+    pub fn connect(endpoint: &str) -> io::Result<Self> {
+        Self::connect_with_logger(endpoint, stdout_logger())
+    }
+
+    /// Like `connect`, reporting to `logger` instead of stdout.
+    pub fn connect_with_logger(endpoint: &str, logger: Arc<dyn Logger>) -> io::Result<Self> {
+        let network = NetworkConnection::open(endpoint, &NETWORK_CONNECTIONS, Arc::clone(&logger))?;
+        ACTIVE_CONNECTIONS.fetch_add(1, Ordering::SeqCst);
+
+        Ok(ConnectionGuard {
+            traffic: network.traffic.clone(),
+            network: ResourceGuard::new(network),
+            endpoint: endpoint.to_string(),
+            logger,
+            poisoned: Cell::new(false),
+            releases: Rc::new(Cell::new(0)),
+            on_event: None,
+            on_close: None,
+        })
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
     pub fn send_data(&self, data: &str) -> io::Result<()> {
-        if let Some(network) = &self.network {
-            network.send_data(data)
-        } else {
-            Err(io::Error::new(
+        if self.poisoned.get() {
+            return Err(io::Error::other("connection poisoned"));
+        }
+
+        let result = match self.network.get() {
+            Some(network) => network.send_data(data),
+            None => Err(io::Error::new(
                 io::ErrorKind::BrokenPipe,
                 "Connection closed",
-            ))
+            )),
+        };
+        if result.is_err() {
+            self.poisoned.set(true);
+        }
+
+        result
+    }
+
+    /// Like `send_data`, but a broken or poisoned connection is replaced once with `reconnect`
+    /// and the send retried before the error is given back.
+    pub fn send_data_with_reconnect(&mut self, data: &str) -> io::Result<()> {
+        match self.send_data(data) {
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe || self.is_poisoned() => {
+                self.logger.log(&format!(
+                    "Reconnecting to {} after: {}",
+                    self.endpoint(),
+                    err
+                ));
+                self.reconnect()?;
+                self.send_data(data)
+            }
+            result => result,
         }
     }
 
-    fn close(&mut self) {
-        if let Some(mut network) = self.network.take() {
-            network.close();
+    /// Number of guards currently alive in the process.
+    pub fn active_count() -> usize {
+        ACTIVE_CONNECTIONS.load(Ordering::SeqCst)
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.get()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.network
+            .get()
+            .is_some_and(NetworkConnection::is_connected)
+    }
+
+    /// Simulates the peer going away: the connection stays open on our side, but the next
+    /// send fails.
+    #[cfg(test)]
+    fn break_connection(&mut self) {
+        if let Some(network) = self.network.get_mut() {
+            network.connected = false;
+        }
+    }
+
+    pub fn stats(&self) -> ConnectionStats {
+        match self.network.get() {
+            Some(network) => network.stats(),
+            None => self.traffic.stats(),
+        }
+    }
+
+    /// How many connections the guard has released, shared so it can still be read once the
+    /// guard is gone.
+    pub fn releases(&self) -> Rc<Cell<u32>> {
+        Rc::clone(&self.releases)
+    }
+
+    /// Calls `f` with the final stats when the guard is dropped.
+    pub fn on_close(&mut self, f: impl FnOnce(ConnectionStats) + 'static) {
+        self.on_close = Some(Box::new(f));
+    }
+
+    /// Calls `f` with every lifecycle message the guard prints, such as closing the connection.
+    pub fn on_event(&mut self, f: impl Fn(&str) + 'static) {
+        self.on_event = Some(Box::new(f));
+    }
+
+    /// Closes the connection now instead of waiting for the drop. Later sends fail, and
+    /// closing again, or dropping the guard, does nothing more.
+    pub fn close(&mut self) -> io::Result<()> {
+        self.release();
+        Ok(())
+    }
+
+    /// Replaces the connection with a fresh, unpoisoned one to the same endpoint, keeping the
+    /// traffic stats. If that fails the guard stays disconnected and poisoned.
+    pub fn reconnect(&mut self) -> io::Result<()> {
+        self.release();
+        self.poisoned.set(true);
+
+        let mut fresh = NetworkConnection::open(
+            &self.endpoint,
+            &NETWORK_CONNECTIONS,
+            Arc::clone(&self.logger),
+        )?;
+        fresh.traffic = self.traffic.clone();
+
+        self.network.replace(fresh);
+        self.poisoned.set(false);
+        Ok(())
+    }
+
+    fn release(&mut self) {
+        let Some(network) = self.network.get() else {
+            return;
+        };
+        self.traffic = network.traffic.clone();
+
+        self.emit("Closing network connection");
+        if self.network.release() {
+            self.releases.set(self.releases.get() + 1);
+        }
+    }
+
+    fn emit(&self, event: &str) {
+        self.logger.log(event);
+
+        if let Some(on_event) = &self.on_event {
+            on_event(event);
         }
     }
 }
 
+impl Default for ConnectionGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Drop for ConnectionGuard {
     fn drop(&mut self) {
-        println!("ConnectionGuard is dropping");
-        self.close();
+        self.emit("ConnectionGuard is dropping");
+        self.release();
+
+        if let Some(on_close) = self.on_close.take() {
+            on_close(self.stats());
+        }
+        ACTIVE_CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
     }
 }
 
-#[cfg(test)]
-mod test {
-    use crate::patterns::raii_guard::ConnectionGuard;
+/// Runs `f` with a connection to the default endpoint that only lives for the call. The
+/// connection is closed before returning, whether `f` succeeds, fails or panics.
+pub fn with_connection<T>(f: impl FnOnce(&ConnectionGuard) -> io::Result<T>) -> io::Result<T> {
+    with_connection_to(&ConnectOptions::default().endpoint(), f)
+}
 
-    #[test]
-    fn test_raii_guard() {
-        let connection_guard = ConnectionGuard::new();
+pub fn with_connection_to<T>(
+    endpoint: &str,
+    f: impl FnOnce(&ConnectionGuard) -> io::Result<T>,
+) -> io::Result<T> {
+    scoped(endpoint, None, f)
+}
 
-        assert!(connection_guard.send_data("Something New!").is_ok());
+fn scoped<T>(
+    endpoint: &str,
+    on_event: Option<EventHook>,
+    f: impl FnOnce(&ConnectionGuard) -> io::Result<T>,
+) -> io::Result<T> {
+    let mut guard = ConnectionGuard::connect(endpoint)?;
+    guard.on_event = on_event;
+
+    // Unwinding would drop the guard anyway; catching the panic makes the order explicit,
+    // so the connection is closed before the panic carries on to the caller.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| f(&guard)));
+    drop(guard);
+
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// Owns several guards and closes them in an explicit order when dropped, for resources
+/// that depend on each other. Guards left out of `close_order` close afterwards, newest first,
+/// matching Rust's own drop order.
+pub struct GuardGroup {
+    guards: Vec<Option<ConnectionGuard>>,
+    order: Vec<usize>,
+    on_close: Option<Box<dyn FnMut(usize)>>,
+}
+
+impl GuardGroup {
+    pub fn new() -> Self {
+        GuardGroup {
+            guards: Vec::new(),
+            order: Vec::new(),
+            on_close: None,
+        }
+    }
+
+    /// Adds a guard, returning the index to refer to it by in `close_order`.
+    pub fn push(&mut self, guard: ConnectionGuard) -> usize {
+        self.guards.push(Some(guard));
+        self.guards.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.guards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.guards.is_empty()
+    }
+
+    /// Sets the indices to close first, in order. Unknown or repeated indices are ignored.
+    pub fn close_order(&mut self, order: Vec<usize>) {
+        self.order = order;
+    }
+
+    /// Calls `f` with each guard's index just before it is closed.
+    pub fn on_close(&mut self, f: impl FnMut(usize) + 'static) {
+        self.on_close = Some(Box::new(f));
+    }
+
+    fn close(&mut self, index: usize) {
+        let Some(guard) = self.guards.get_mut(index).and_then(Option::take) else {
+            return;
+        };
+
+        if let Some(on_close) = &mut self.on_close {
+            on_close(index);
+        }
+        drop(guard);
+    }
+}
+
+impl Default for GuardGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for GuardGroup {
+    fn drop(&mut self) {
+        for index in std::mem::take(&mut self.order) {
+            self.close(index);
+        }
+
+        for index in (0..self.guards.len()).rev() {
+            self.close(index);
+        }
+    }
+}
+
+/// Where `BufferedConnectionGuard` sends its buffered payloads, all in one go.
+pub trait Transport {
+    fn transmit(&mut self, payloads: &[String]) -> io::Result<()>;
+
+    /// Called once, after the final flush, when the guard is dropped.
+    fn close(&mut self) {}
+}
+
+impl Transport for NetworkConnection {
+    fn transmit(&mut self, payloads: &[String]) -> io::Result<()> {
+        payloads
+            .iter()
+            .try_for_each(|payload| self.send_data(payload))
+    }
+
+    fn close(&mut self) {
+        self.logger.log("Closing network connection");
+        self.release();
+    }
+}
+
+type FlushErrorHook = Box<dyn Fn(&io::Error)>;
+
+/// Holds sent payloads back until `flush`, until `max_buffered` are waiting, or until the
+/// guard is dropped. A failed flush keeps the payloads for the next attempt. `Drop` can't
+/// return the error of its final flush, so it is reported to `on_flush_error` instead, and
+/// dropped silently if no hook is set. Either way the transport is closed afterwards.
+pub struct BufferedConnectionGuard<T: Transport = NetworkConnection> {
+    transport: T,
+    buffer: Vec<String>,
+    max_buffered: usize,
+    on_flush_error: Option<FlushErrorHook>,
+}
+
+impl BufferedConnectionGuard {
+    pub fn connect(endpoint: &str, max_buffered: usize) -> io::Result<Self> {
+        Self::connect_with_logger(endpoint, max_buffered, stdout_logger())
+    }
+
+    /// Like `connect`, reporting to `logger` instead of stdout.
+    pub fn connect_with_logger(
+        endpoint: &str,
+        max_buffered: usize,
+        logger: Arc<dyn Logger>,
+    ) -> io::Result<Self> {
+        Ok(Self::new(
+            NetworkConnection::open(endpoint, &NETWORK_CONNECTIONS, logger)?,
+            max_buffered,
+        ))
+    }
+}
+
+impl<T: Transport> BufferedConnectionGuard<T> {
+    pub fn new(transport: T, max_buffered: usize) -> Self {
+        BufferedConnectionGuard {
+            transport,
+            buffer: Vec::new(),
+            max_buffered,
+            on_flush_error: None,
+        }
+    }
+
+    /// Buffers `data`, flushing once `max_buffered` payloads are waiting.
+    pub fn send_data(&mut self, data: &str) -> io::Result<()> {
+        self.buffer.push(data.to_string());
+
+        if self.buffer.len() >= self.max_buffered {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        self.transport.transmit(&self.buffer)?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn on_flush_error(&mut self, f: impl Fn(&io::Error) + 'static) {
+        self.on_flush_error = Some(Box::new(f));
+    }
+}
+
+impl<T: Transport> Drop for BufferedConnectionGuard<T> {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            if let Some(on_flush_error) = &self.on_flush_error {
+                on_flush_error(&err);
+            }
+        }
+
+        self.transport.close();
+    }
+}
+
+/// A fixed set of open connections shared by checking them out. A checkout is a guard that
+/// hands its connection back to the pool when dropped instead of closing it; the pool closes
+/// them all when it is dropped itself.
+pub struct ConnectionPool {
+    idle: Mutex<Vec<NetworkConnection>>,
+    returned: Condvar,
+}
+
+impl ConnectionPool {
+    /// Opens `size` connections to the default endpoint.
+    pub fn new(size: usize) -> io::Result<Self> {
+        Self::connect(&ConnectOptions::default().endpoint(), size)
+    }
+
+    pub fn connect(endpoint: &str, size: usize) -> io::Result<Self> {
+        Self::connect_with_logger(endpoint, size, stdout_logger())
+    }
+
+    /// Like `connect`, with every connection reporting to `logger` instead of stdout.
+    pub fn connect_with_logger(
+        endpoint: &str,
+        size: usize,
+        logger: Arc<dyn Logger>,
+    ) -> io::Result<Self> {
+        Self::open(endpoint, size, &NETWORK_CONNECTIONS, logger)
+    }
+
+    #[cfg(test)]
+    fn connect_tracked(
+        endpoint: &str,
+        size: usize,
+        detector: &'static LeakDetector,
+    ) -> io::Result<Self> {
+        Self::open(endpoint, size, detector, stdout_logger())
+    }
+
+    fn open(
+        endpoint: &str,
+        size: usize,
+        detector: &'static LeakDetector,
+        logger: Arc<dyn Logger>,
+    ) -> io::Result<Self> {
+        let idle = (0..size)
+            .map(|_| NetworkConnection::open(endpoint, detector, Arc::clone(&logger)))
+            .collect::<io::Result<_>>()?;
+
+        Ok(ConnectionPool {
+            idle: Mutex::new(idle),
+            returned: Condvar::new(),
+        })
+    }
+
+    /// Number of connections not checked out.
+    pub fn available(&self) -> usize {
+        self.lock_idle().len()
+    }
+
+    /// Checks out a connection if one is free, without waiting.
+    pub fn try_checkout(&self) -> Option<PooledConnection<'_>> {
+        let connection = self.lock_idle().pop()?;
+        Some(PooledConnection::new(self, connection))
+    }
+
+    /// Waits up to `timeout` for a connection to be handed back, failing with
+    /// `ErrorKind::TimedOut` if none is.
+    pub fn checkout_timeout(&self, timeout: Duration) -> io::Result<PooledConnection<'_>> {
+        let (mut idle, _) = self
+            .returned
+            .wait_timeout_while(self.lock_idle(), timeout, |idle| idle.is_empty())
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match idle.pop() {
+            Some(connection) => Ok(PooledConnection::new(self, connection)),
+            None => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no connection available within {:?}", timeout),
+            )),
+        }
+    }
+
+    fn lock_idle(&self) -> MutexGuard<'_, Vec<NetworkConnection>> {
+        self.idle.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for ConnectionPool {
+    fn drop(&mut self) {
+        for mut connection in self.lock_idle().drain(..) {
+            connection.logger.log("Closing network connection");
+            connection.release();
+        }
+    }
+}
+
+pub struct PooledConnection<'a> {
+    pool: &'a ConnectionPool,
+    connection: Option<NetworkConnection>,
+}
+
+impl<'a> PooledConnection<'a> {
+    fn new(pool: &'a ConnectionPool, connection: NetworkConnection) -> Self {
+        PooledConnection {
+            pool,
+            connection: Some(connection),
+        }
+    }
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = NetworkConnection;
+
+    fn deref(&self) -> &NetworkConnection {
+        self.connection.as_ref().expect("only taken on drop")
+    }
+}
+
+impl DerefMut for PooledConnection<'_> {
+    fn deref_mut(&mut self) -> &mut NetworkConnection {
+        self.connection.as_mut().expect("only taken on drop")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            self.pool.lock_idle().push(connection);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use self::asynchronous::AsyncConnectionGuard;
+
+/// `Drop` can't `.await`, so async cleanup has to be handed to the runtime. The spawned task
+/// outlives the guard, may run after the next statement, and never runs if the runtime is
+/// shutting down. Without a runtime at all the guard falls back to closing synchronously.
+#[cfg(feature = "async")]
+mod asynchronous {
+    use std::io;
+    use std::sync::Arc;
+
+    use tokio::runtime::Handle;
+    use tokio::sync::oneshot;
+
+    use super::{stdout_logger, Logger};
+
+    struct NetworkConnection {
+        connected: bool,
+        logger: Arc<dyn Logger>,
+    }
+
+    impl NetworkConnection {
+        async fn connect(logger: Arc<dyn Logger>) -> Self {
+            NetworkConnection {
+                connected: true,
+                logger,
+            }
+        }
+
+        async fn send_data(&self, data: &str) -> io::Result<()> {
+            if self.connected {
+                self.logger
+                    .log(&format!("Sending data over the network: {}", data));
+                Ok(())
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "Connection closed",
+                ))
+            }
+        }
+
+        async fn shutdown(&mut self) {
+            tokio::task::yield_now().await;
+            self.close();
+        }
+
+        fn close(&mut self) {
+            self.logger.log("Closing network connection");
+            self.connected = false;
+        }
+    }
+
+    pub struct AsyncConnectionGuard {
+        logger: Arc<dyn Logger>,
+        network: Option<NetworkConnection>,
+        closed: Option<oneshot::Sender<()>>,
+    }
+
+    impl AsyncConnectionGuard {
+        pub async fn connect() -> Self {
+            Self::connect_with_logger(stdout_logger()).await
+        }
+
+        pub async fn connect_with_logger(logger: Arc<dyn Logger>) -> Self {
+            AsyncConnectionGuard {
+                network: Some(NetworkConnection::connect(Arc::clone(&logger)).await),
+                logger,
+                closed: None,
+            }
+        }
+
+        pub async fn send_data(&self, data: &str) -> io::Result<()> {
+            if let Some(network) = &self.network {
+                network.send_data(data).await
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "Connection closed",
+                ))
+            }
+        }
+
+        /// Resolves once the connection has been closed after the guard is dropped. It errors
+        /// instead if the cleanup task was dropped before it ran.
+        pub fn on_close(&mut self) -> oneshot::Receiver<()> {
+            let (sender, receiver) = oneshot::channel();
+            self.closed = Some(sender);
+            receiver
+        }
+    }
+
+    impl Drop for AsyncConnectionGuard {
+        fn drop(&mut self) {
+            self.logger.log("AsyncConnectionGuard is dropping");
+
+            let Some(mut network) = self.network.take() else {
+                return;
+            };
+            let closed = self.closed.take();
+
+            match Handle::try_current() {
+                Ok(handle) => {
+                    handle.spawn(async move {
+                        network.shutdown().await;
+                        if let Some(closed) = closed {
+                            let _ = closed.send(());
+                        }
+                    });
+                }
+                Err(_) => {
+                    network.close();
+                    if let Some(closed) = closed {
+                        let _ = closed.send(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::{Cell, RefCell};
+    use std::io;
+    use std::mem;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::patterns::raii_guard::{
+        scoped, with_connection, with_connection_to, BufferedConnectionGuard, ConnectOptions,
+        ConnectOptionsError, ConnectionGuard, ConnectionPool, EventHook, FakeFileHandle,
+        GuardGroup, LeakDetector, NetworkConnection, ResourceGuard, Transport, VecLogger,
+    };
+
+    // The active count is process-wide, so tests creating guards must not overlap.
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn serial() -> MutexGuard<'static, ()> {
+        SERIAL
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    #[test]
+    fn test_raii_guard() {
+        let _serial = serial();
+        let connection_guard = ConnectionGuard::new();
+
+        assert!(connection_guard.send_data("Something New!").is_ok());
+    }
+
+    #[test]
+    fn test_connect() {
+        let _serial = serial();
+        let connection_guard = ConnectionGuard::connect("db.example:5432").unwrap();
+
+        assert_eq!(connection_guard.endpoint(), "db.example:5432");
+        assert!(connection_guard.send_data("Something New!").is_ok());
+    }
+
+    #[test]
+    fn test_connect_refused() {
+        let _serial = serial();
+        let baseline = ConnectionGuard::active_count();
+
+        let err = ConnectionGuard::connect("unreachable.example:5432")
+            .map(|_| ())
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+        assert_eq!(ConnectionGuard::active_count(), baseline);
+
+        let connection_guard = ConnectionGuard::new();
+        assert_eq!(connection_guard.endpoint(), "localhost:8080");
+        assert!(connection_guard.send_data("Something New!").is_ok());
+    }
+
+    #[test]
+    fn test_connect_options_validation() {
+        assert_eq!(
+            ConnectOptions::builder().host("  ").build(),
+            Err(ConnectOptionsError::EmptyHost)
+        );
+        assert_eq!(
+            ConnectOptions::builder().port(0).build(),
+            Err(ConnectOptionsError::ZeroPort)
+        );
+        assert_eq!(
+            ConnectOptionsError::ZeroPort.to_string(),
+            "port must be between 1 and 65535"
+        );
+        assert_eq!(
+            ConnectOptions::builder().build(),
+            Ok(ConnectOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_default_options() {
+        let _serial = serial();
+        let connection_guard = ConnectionGuard::with_options(&ConnectOptions::default()).unwrap();
+
+        assert_eq!(
+            connection_guard.endpoint(),
+            ConnectionGuard::new().endpoint()
+        );
+        assert!(connection_guard.send_data("Something New!").is_ok());
+    }
+
+    #[test]
+    fn test_with_options() {
+        let _serial = serial();
+        let options = ConnectOptions::builder()
+            .host("db.example")
+            .port(5432)
+            .timeout(Duration::from_secs(5))
+            .retries(2)
+            .build()
+            .unwrap();
+        let logger = Arc::new(VecLogger::new());
+        let connection_guard =
+            ConnectionGuard::with_options_and_logger(&options, logger.clone()).unwrap();
+
+        connection_guard.send_data("first").unwrap();
+        connection_guard.send_data("second").unwrap();
+
+        assert_eq!(connection_guard.endpoint(), "db.example:5432");
+        assert_eq!(
+            logger.messages(),
+            vec![
+                "Connected to db.example:5432",
+                "Sending data over the network to db.example:5432: first",
+                "Sending data over the network to db.example:5432: second",
+            ]
+        );
+
+        let unreachable = ConnectOptions::builder()
+            .host("unreachable.example")
+            .retries(2)
+            .build()
+            .unwrap();
+        let err = ConnectionGuard::with_options(&unreachable)
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+    }
+
+    #[test]
+    fn test_with_options_timeout() {
+        let _serial = serial();
+        let logger = Arc::new(VecLogger::new());
+        let options = ConnectOptions::builder()
+            .host("unreachable.example")
+            .timeout(Duration::from_millis(10))
+            .retries(u32::MAX)
+            .build()
+            .unwrap();
+
+        let err = ConnectionGuard::with_options_and_logger(&options, logger.clone())
+            .map(|_| ())
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(
+            logger.messages(),
+            vec![
+                "Retrying unreachable.example:8080 after: \
+                 connection to unreachable.example:8080 refused"
+            ]
+        );
+    }
+
+    fn record_events(guard: &mut ConnectionGuard) -> Rc<RefCell<Vec<String>>> {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let recorder = Rc::clone(&events);
+        guard.on_event(move |event| recorder.borrow_mut().push(event.to_string()));
+        events
+    }
+
+    #[test]
+    fn test_close() {
+        let _serial = serial();
+        let mut connection_guard = ConnectionGuard::new();
+        let events = record_events(&mut connection_guard);
+
+        connection_guard.close().unwrap();
+
+        let err = connection_guard.send_data("too late").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(*events.borrow(), vec!["Closing network connection"]);
+    }
+
+    #[test]
+    fn test_close_twice_then_drop() {
+        let _serial = serial();
+        let mut connection_guard = ConnectionGuard::new();
+        let events = record_events(&mut connection_guard);
+
+        connection_guard.close().unwrap();
+        connection_guard.close().unwrap();
+        assert_eq!(*events.borrow(), vec!["Closing network connection"]);
+
+        drop(connection_guard);
+        assert_eq!(
+            *events.borrow(),
+            vec!["Closing network connection", "ConnectionGuard is dropping"]
+        );
+    }
+
+    #[test]
+    fn test_reconnect_after_close() {
+        let _serial = serial();
+        let mut connection_guard = ConnectionGuard::connect("db.example:5432").unwrap();
+        connection_guard.send_data("before").unwrap();
+
+        connection_guard.close().unwrap();
+        assert!(!connection_guard.is_connected());
+
+        connection_guard.reconnect().unwrap();
+        assert!(connection_guard.is_connected());
+        assert!(connection_guard.send_data("Something New!").is_ok());
+        assert_eq!(connection_guard.endpoint(), "db.example:5432");
+        assert_eq!(connection_guard.stats().messages_sent, 2);
+    }
+
+    #[test]
+    fn test_send_data_with_reconnect() {
+        let _serial = serial();
+        let mut connection_guard = ConnectionGuard::new();
+
+        connection_guard.break_connection();
+        assert!(!connection_guard.is_connected());
+
+        connection_guard
+            .send_data_with_reconnect("retried")
+            .unwrap();
+
+        assert!(connection_guard.is_connected());
+        assert!(!connection_guard.is_poisoned());
+        assert_eq!(connection_guard.stats().messages_sent, 1);
+
+        connection_guard.break_connection();
+        let err = connection_guard.send_data("no retry").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_send_data_with_reconnect_when_poisoned() {
+        let _serial = serial();
+        let mut connection_guard = ConnectionGuard::new();
+
+        connection_guard.close().unwrap();
+        assert!(connection_guard.send_data("lost").is_err());
+        assert!(connection_guard.is_poisoned());
+
+        connection_guard
+            .send_data_with_reconnect("retried")
+            .unwrap();
+
+        assert!(connection_guard.is_connected());
+        assert!(!connection_guard.is_poisoned());
+    }
+
+    #[test]
+    fn test_resource_guard_releases_on_drop() {
+        let guard = ResourceGuard::<FakeFileHandle>::acquire().unwrap();
+        let releases = guard.get().unwrap().releases();
+
+        assert_eq!(releases.get(), 0);
+        drop(guard);
+        assert_eq!(releases.get(), 1);
+    }
+
+    #[test]
+    fn test_resource_guard_releases_once() {
+        let mut guard = ResourceGuard::<FakeFileHandle>::acquire().unwrap();
+        let releases = guard.get().unwrap().releases();
+
+        assert!(guard.release());
+        assert!(!guard.release());
+        assert!(guard.get().is_none());
+        drop(guard);
+
+        assert_eq!(releases.get(), 1);
+    }
+
+    #[test]
+    fn test_connection_guard_releases_once() {
+        let _serial = serial();
+        let mut connection_guard = ConnectionGuard::new();
+        let releases = connection_guard.releases();
+
+        connection_guard.close().unwrap();
+        assert_eq!(releases.get(), 1);
+
+        connection_guard.close().unwrap();
+        drop(connection_guard);
+        assert_eq!(releases.get(), 1);
+
+        let mut connection_guard = ConnectionGuard::new();
+        let releases = connection_guard.releases();
+
+        connection_guard.reconnect().unwrap();
+        assert_eq!(releases.get(), 1);
+
+        drop(connection_guard);
+        assert_eq!(releases.get(), 2);
+    }
+
+    #[test]
+    fn test_resource_guard_over_connection() {
+        let mut guard = ResourceGuard::<NetworkConnection>::acquire().unwrap();
+        assert!(guard.get().is_some_and(|network| network.connected));
+
+        guard.replace(NetworkConnection::connect("db.example:5432").unwrap());
+        assert!(guard.get().is_some_and(|network| network.connected));
+        assert!(guard.release());
+
+        let _serial = serial();
+        let mut connection_guard = ConnectionGuard::new();
+        let events = record_events(&mut connection_guard);
+
+        drop(connection_guard);
+        assert_eq!(
+            *events.borrow(),
+            vec!["ConnectionGuard is dropping", "Closing network connection"]
+        );
+    }
+
+    fn close_counter() -> (Rc<Cell<u32>>, Option<EventHook>) {
+        let closes = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&closes);
+        let on_event: EventHook = Box::new(move |event| {
+            if event == "Closing network connection" {
+                counter.set(counter.get() + 1);
+            }
+        });
+
+        (closes, Some(on_event))
+    }
+
+    #[test]
+    fn test_with_connection() {
+        let _serial = serial();
+
+        let stats = with_connection(|guard| {
+            guard.send_data("scoped")?;
+            Ok(guard.stats())
+        })
+        .unwrap();
+        assert_eq!(stats.messages_sent, 1);
+
+        let endpoint =
+            with_connection_to("db.example:5432", |guard| Ok(guard.endpoint().to_string()))
+                .unwrap();
+        assert_eq!(endpoint, "db.example:5432");
+    }
+
+    #[test]
+    fn test_with_connection_closes_on_error() {
+        let _serial = serial();
+        let baseline = ConnectionGuard::active_count();
+        let (closes, on_event) = close_counter();
+
+        let err = scoped("db.example:5432", on_event, |_| -> io::Result<()> {
+            Err(io::Error::other("query failed"))
+        })
+        .unwrap_err();
+
+        assert_eq!(err.to_string(), "query failed");
+        assert_eq!(closes.get(), 1);
+        assert_eq!(ConnectionGuard::active_count(), baseline);
+    }
+
+    #[test]
+    fn test_with_connection_closes_on_panic() {
+        let _serial = serial();
+        let (closes, on_event) = close_counter();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            scoped("db.example:5432", on_event, |_| -> io::Result<()> {
+                panic!("closure panicked")
+            })
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(closes.get(), 1);
+    }
+
+    #[test]
+    fn test_leak_detector_clean() {
+        static DETECTOR: LeakDetector = LeakDetector::new();
+
+        let network = NetworkConnection::connect_tracked("db.example:5432", &DETECTOR).unwrap();
+        assert_eq!(DETECTOR.live(), 1);
+
+        let mut guard = ResourceGuard::new(network);
+        guard.release();
+        assert_eq!(DETECTOR.live(), 0);
+
+        drop(NetworkConnection::connect_tracked("db.example:5432", &DETECTOR).unwrap());
+        DETECTOR.assert_no_leaks();
+    }
+
+    #[test]
+    fn test_leak_detector_catches_forget() {
+        static DETECTOR: LeakDetector = LeakDetector::new();
+
+        let network = NetworkConnection::connect_tracked("db.example:5432", &DETECTOR).unwrap();
+        mem::forget(network);
+
+        assert_eq!(DETECTOR.live(), 1);
+        let result = panic::catch_unwind(|| DETECTOR.assert_no_leaks());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_connection_pool_checkout() {
+        let pool = ConnectionPool::connect("db.example:5432", 2).unwrap();
+
+        let first = pool.try_checkout().unwrap();
+        let second = pool.try_checkout().unwrap();
+        assert!(pool.try_checkout().is_none());
+        assert_eq!(pool.available(), 0);
+
+        drop(first);
+        assert_eq!(pool.available(), 1);
+
+        let third = pool.try_checkout().unwrap();
+        assert!(third.is_connected());
+        assert!(third.send_data("reused").is_ok());
+        assert_eq!(second.endpoint(), "db.example:5432");
+    }
+
+    #[test]
+    fn test_connection_pool_checkout_timeout() {
+        let pool = ConnectionPool::new(1).unwrap();
+        let held = pool.try_checkout().unwrap();
+
+        let err = pool
+            .checkout_timeout(Duration::from_millis(10))
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                drop(held);
+            });
+
+            assert!(pool.checkout_timeout(Duration::from_secs(5)).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_connection_pool_closes_on_drop() {
+        static DETECTOR: LeakDetector = LeakDetector::new();
+
+        let pool = ConnectionPool::connect_tracked("db.example:5432", 3, &DETECTOR).unwrap();
+        drop(pool.try_checkout().unwrap());
+        assert_eq!(DETECTOR.live(), 3);
+
+        drop(pool);
+        DETECTOR.assert_no_leaks();
+    }
+
+    type Transmissions = Rc<RefCell<Vec<Vec<String>>>>;
+
+    struct RecordingTransport {
+        transmissions: Transmissions,
+        fail: bool,
+        closed: Rc<Cell<bool>>,
+    }
+
+    impl RecordingTransport {
+        fn new(fail: bool) -> (Self, Transmissions) {
+            let transmissions = Rc::new(RefCell::new(Vec::new()));
+            let transport = RecordingTransport {
+                transmissions: Rc::clone(&transmissions),
+                fail,
+                closed: Rc::new(Cell::new(false)),
+            };
+
+            (transport, transmissions)
+        }
+
+        fn closed(&self) -> Rc<Cell<bool>> {
+            Rc::clone(&self.closed)
+        }
+    }
+
+    impl Transport for RecordingTransport {
+        fn transmit(&mut self, payloads: &[String]) -> io::Result<()> {
+            if self.fail {
+                return Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "Connection closed",
+                ));
+            }
+
+            self.transmissions.borrow_mut().push(payloads.to_vec());
+            Ok(())
+        }
+
+        fn close(&mut self) {
+            self.closed.set(true);
+        }
+    }
+
+    #[test]
+    fn test_buffered_flushes_on_drop() {
+        let (transport, transmissions) = RecordingTransport::new(false);
+        let closed = transport.closed();
+        let mut guard = BufferedConnectionGuard::new(transport, 5);
+
+        guard.send_data("one").unwrap();
+        guard.send_data("two").unwrap();
+        guard.send_data("three").unwrap();
+        assert!(transmissions.borrow().is_empty());
+        assert!(!closed.get());
+
+        drop(guard);
+        assert_eq!(*transmissions.borrow(), vec![vec!["one", "two", "three"]]);
+        assert!(closed.get());
+    }
+
+    #[test]
+    fn test_buffered_flushes_at_threshold() {
+        let (transport, transmissions) = RecordingTransport::new(false);
+        let mut guard = BufferedConnectionGuard::new(transport, 2);
+
+        guard.send_data("one").unwrap();
+        guard.send_data("two").unwrap();
+        guard.send_data("three").unwrap();
+
+        assert_eq!(*transmissions.borrow(), vec![vec!["one", "two"]]);
+        assert_eq!(guard.buffered(), 1);
+    }
+
+    #[test]
+    fn test_buffered_explicit_flush() {
+        let (transport, transmissions) = RecordingTransport::new(false);
+        let mut guard = BufferedConnectionGuard::new(transport, 5);
+
+        guard.send_data("one").unwrap();
+        guard.flush().unwrap();
+        assert_eq!(guard.buffered(), 0);
+        guard.flush().unwrap();
+
+        drop(guard);
+        assert_eq!(*transmissions.borrow(), vec![vec!["one"]]);
+    }
+
+    #[test]
+    fn test_buffered_drop_records_flush_error() {
+        let (transport, _) = RecordingTransport::new(true);
+        let closed = transport.closed();
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        let recorder = Rc::clone(&errors);
+
+        let mut guard = BufferedConnectionGuard::new(transport, 5);
+        guard.on_flush_error(move |err| recorder.borrow_mut().push(err.kind()));
+
+        guard.send_data("lost").unwrap();
+        assert_eq!(guard.flush().unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(guard.buffered(), 1);
+
+        drop(guard);
+        assert_eq!(*errors.borrow(), vec![io::ErrorKind::BrokenPipe]);
+        assert!(closed.get());
+    }
+
+    #[test]
+    fn test_buffered_over_network() {
+        let mut guard = BufferedConnectionGuard::connect("db.example:5432", 2).unwrap();
+
+        guard.send_data("one").unwrap();
+        guard.send_data("two").unwrap();
+        assert_eq!(guard.buffered(), 0);
+    }
+
+    #[test]
+    fn test_connection_stats() {
+        let _serial = serial();
+        let mut connection_guard = ConnectionGuard::new();
+        let closed_with = Rc::new(Cell::new(None));
+        let recorder = Rc::clone(&closed_with);
+        connection_guard.on_close(move |stats| recorder.set(Some(stats)));
+
+        connection_guard.send_data("hello").unwrap();
+        connection_guard.send_data("héllo wörld").unwrap();
+
+        let stats = connection_guard.stats();
+        assert_eq!(stats.messages_sent, 2);
+        assert_eq!(stats.bytes_sent, 5 + 13);
+
+        drop(connection_guard);
+
+        let closed_with = closed_with.get().unwrap();
+        assert_eq!(closed_with.messages_sent, 2);
+        assert_eq!(closed_with.bytes_sent, 18);
+        assert!(closed_with.connection_duration >= stats.connection_duration);
+    }
+
+    #[test]
+    fn test_logger() {
+        let _serial = serial();
+        let logger = Arc::new(VecLogger::new());
+        let connection_guard =
+            ConnectionGuard::connect_with_logger("db.example:5432", logger.clone()).unwrap();
+
+        connection_guard.send_data("hello").unwrap();
+        drop(connection_guard);
+
+        assert_eq!(
+            logger.messages(),
+            vec![
+                "Connected to db.example:5432",
+                "Sending data over the network to db.example:5432: hello",
+                "ConnectionGuard is dropping",
+                "Closing network connection",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_logger_with_options() {
+        let _serial = serial();
+        let logger = Arc::new(VecLogger::new());
+        let unreachable = ConnectOptions::builder()
+            .host("unreachable.example")
+            .retries(2)
+            .build()
+            .unwrap();
+
+        assert!(ConnectionGuard::with_options_and_logger(&unreachable, logger.clone()).is_err());
+        assert_eq!(
+            logger.messages(),
+            vec![
+                "Retrying unreachable.example:8080 after: connection to unreachable.example:8080 refused",
+                "Retrying unreachable.example:8080 after: connection to unreachable.example:8080 refused",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_logger_for_pool_and_buffered() {
+        let logger = Arc::new(VecLogger::new());
+
+        let pool =
+            ConnectionPool::connect_with_logger("db.example:5432", 2, logger.clone()).unwrap();
+        pool.try_checkout().unwrap().send_data("pooled").unwrap();
+        drop(pool);
+
+        let mut buffered =
+            BufferedConnectionGuard::connect_with_logger("cache.example:6379", 5, logger.clone())
+                .unwrap();
+        buffered.send_data("buffered").unwrap();
+        drop(buffered);
+
+        assert_eq!(
+            logger.messages(),
+            vec![
+                "Connected to db.example:5432",
+                "Connected to db.example:5432",
+                "Sending data over the network to db.example:5432: pooled",
+                "Closing network connection",
+                "Closing network connection",
+                "Connected to cache.example:6379",
+                "Sending data over the network to cache.example:6379: buffered",
+                "Closing network connection",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_logger_follows_reconnect() {
+        let _serial = serial();
+        let logger = Arc::new(VecLogger::new());
+        let mut connection_guard =
+            ConnectionGuard::connect_with_logger("db.example:5432", logger.clone()).unwrap();
+
+        connection_guard.reconnect().unwrap();
+        connection_guard.send_data("again").unwrap();
+
+        assert_eq!(
+            logger.messages(),
+            vec![
+                "Connected to db.example:5432",
+                "Closing network connection",
+                "Connected to db.example:5432",
+                "Sending data over the network to db.example:5432: again",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_poisoned_until_reconnect() {
+        let _serial = serial();
+        let mut connection_guard = ConnectionGuard::new();
+
+        connection_guard.close().unwrap();
+
+        let err = connection_guard.send_data("lost").unwrap_err();
+        assert_eq!(err.to_string(), "Connection closed");
+        assert!(connection_guard.is_poisoned());
+
+        let err = connection_guard.send_data("blocked").unwrap_err();
+        assert_eq!(err.to_string(), "connection poisoned");
+
+        connection_guard.reconnect().unwrap();
+
+        assert!(!connection_guard.is_poisoned());
+        assert!(connection_guard.send_data("Something New!").is_ok());
+    }
+
+    #[test]
+    fn test_active_count() {
+        let _serial = serial();
+        let baseline = ConnectionGuard::active_count();
+
+        let _first = ConnectionGuard::new();
+        {
+            let _second = ConnectionGuard::new();
+            assert_eq!(ConnectionGuard::active_count(), baseline + 2);
+        }
+
+        assert_eq!(ConnectionGuard::active_count(), baseline + 1);
+    }
+
+    #[test]
+    fn test_active_count_across_threads() {
+        let _serial = serial();
+        let baseline = ConnectionGuard::active_count();
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                thread::spawn(|| {
+                    for _ in 0..100 {
+                        let _guard = ConnectionGuard::new();
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(ConnectionGuard::active_count(), baseline);
+    }
+
+    fn recording_group(closed: &Rc<RefCell<Vec<usize>>>) -> GuardGroup {
+        let mut group = GuardGroup::new();
+        let recorder = Rc::clone(closed);
+
+        group.on_close(move |index| recorder.borrow_mut().push(index));
+        for _ in 0..3 {
+            group.push(ConnectionGuard::new());
+        }
+
+        group
+    }
+
+    #[test]
+    fn test_guard_group_close_order() {
+        let _serial = serial();
+        let baseline = ConnectionGuard::active_count();
+        let closed = Rc::new(RefCell::new(Vec::new()));
+
+        {
+            let mut group = recording_group(&closed);
+            group.close_order(vec![1, 0, 2]);
+
+            assert_eq!(group.len(), 3);
+            assert_eq!(ConnectionGuard::active_count(), baseline + 3);
+        }
+
+        assert_eq!(*closed.borrow(), vec![1, 0, 2]);
+        assert_eq!(ConnectionGuard::active_count(), baseline);
+    }
+
+    #[test]
+    fn test_guard_group_default_order() {
+        let _serial = serial();
+        let closed = Rc::new(RefCell::new(Vec::new()));
+
+        {
+            let mut group = recording_group(&closed);
+            group.close_order(vec![0, 7, 0]);
+        }
+
+        assert_eq!(*closed.borrow(), vec![0, 2, 1]);
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod test_async {
+    use std::sync::Arc;
+
+    use crate::patterns::raii_guard::{AsyncConnectionGuard, VecLogger};
+
+    #[tokio::test]
+    async fn test_async_raii_guard() {
+        let closed = {
+            let mut connection_guard = AsyncConnectionGuard::connect().await;
+            let closed = connection_guard.on_close();
+
+            assert!(connection_guard.send_data("Something New!").await.is_ok());
+
+            closed
+        };
+
+        assert!(closed.await.is_ok());
+    }
+
+    #[test]
+    fn test_drop_outside_runtime() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut connection_guard = runtime.block_on(AsyncConnectionGuard::connect());
+        let mut closed = connection_guard.on_close();
+
+        drop(connection_guard);
+
+        assert!(closed.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_async_logger() {
+        let logger = Arc::new(VecLogger::new());
+
+        let closed = {
+            let mut connection_guard =
+                AsyncConnectionGuard::connect_with_logger(logger.clone()).await;
+            let closed = connection_guard.on_close();
+
+            connection_guard.send_data("hello").await.unwrap();
+            closed
+        };
+        closed.await.unwrap();
+
+        assert_eq!(
+            logger.messages(),
+            vec![
+                "Sending data over the network: hello",
+                "AsyncConnectionGuard is dropping",
+                "Closing network connection",
+            ]
+        );
     }
 }
@@ -0,0 +1,76 @@
+use std::sync::{Mutex, PoisonError};
+
+/// A `Mutex` that survives a holder panicking. Instead of every later `lock().unwrap()`
+/// panicking too, the guard is recovered from the `PoisonError` with a warning and the poison
+/// is cleared.
+pub struct SafeLock<T> {
+    inner: Mutex<T>,
+}
+
+impl<T> SafeLock<T> {
+    pub fn new(value: T) -> Self {
+        SafeLock {
+            inner: Mutex::new(value),
+        }
+    }
+
+    /// Runs `f` with the value locked. The lock is released when `f` returns or panics.
+    pub fn with_lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut guard = self.inner.lock().unwrap_or_else(|poisoned| {
+            eprintln!("warning: lock poisoned by a panicking thread, recovering");
+            self.inner.clear_poison();
+            PoisonError::into_inner(poisoned)
+        });
+
+        f(&mut guard)
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::patterns::raii_lock::SafeLock;
+
+    #[test]
+    fn test_with_lock() {
+        let lock = SafeLock::new(1);
+
+        assert_eq!(lock.with_lock(|value| *value + 1), 2);
+        lock.with_lock(|value| *value = 10);
+        assert_eq!(lock.into_inner(), 10);
+    }
+
+    #[test]
+    fn test_recovers_from_poisoning() {
+        let lock = Arc::new(SafeLock::new(vec![1]));
+
+        let panicking = Arc::clone(&lock);
+        let result = thread::spawn(move || {
+            panicking.with_lock(|values| {
+                values.push(2);
+                panic!("panicked while holding the lock");
+            })
+        })
+        .join();
+
+        assert!(result.is_err());
+        assert!(lock.is_poisoned());
+
+        lock.with_lock(|values| values.push(3));
+
+        assert!(!lock.is_poisoned());
+        assert_eq!(lock.with_lock(|values| values.clone()), vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::{Mutex, OnceLock};
+
+// Rust makes mutable globals awkward on purpose: a `static mut` needs `unsafe` for every access.
+// The idiomatic singleton is an immutable value initialised once, with any mutable part
+// wrapped in a synchronisation primitive.
+
+pub struct Config {
+    pub app_name: String,
+    pub max_connections: u32,
+}
+
+impl Config {
+    pub fn global() -> &'static Config {
+        static CONFIG: OnceLock<Config> = OnceLock::new();
+
+        CONFIG.get_or_init(|| Config {
+            app_name: String::from("design-patterns"),
+            max_connections: 10,
+        })
+    }
+}
+
+static COUNTER: Mutex<u64> = Mutex::new(0);
+
+pub fn increment_counter() -> u64 {
+    let mut counter = COUNTER.lock().unwrap();
+    *counter += 1;
+    *counter
+}
+
+pub fn counter() -> u64 {
+    *COUNTER.lock().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use crate::patterns::singleton::{counter, increment_counter, Config};
+
+    #[test]
+    fn test_config_singleton() {
+        let first = Config::global();
+        let second = thread::spawn(Config::global).join().unwrap();
+
+        assert!(std::ptr::eq(first, second));
+        assert_eq!(first.max_connections, 10);
+    }
+
+    #[test]
+    fn test_mutex_counter() {
+        let before = counter();
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                thread::spawn(|| {
+                    for _ in 0..100 {
+                        increment_counter();
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(counter(), before + 800);
+    }
+}
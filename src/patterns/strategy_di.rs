@@ -1,3 +1,9 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
 pub trait Database {
     fn query(&self, query: &str) -> String;
 }
@@ -38,6 +44,357 @@ impl Strategy for SubtractionStrategy {
     }
 }
 
+pub struct MultiplicationStrategy;
+
+impl Strategy for MultiplicationStrategy {
+    fn execute_strategy(&self, a: i32, b: i32) -> i32 {
+        a * b
+    }
+}
+
+/// The built-in strategies as one enum. Like `Box<dyn Strategy>` it is picked at runtime, but
+/// it lives on the stack and dispatches with a `match` the compiler can inline. The cost is
+/// that the set is closed: a new strategy means a new variant, not just a new impl.
+pub enum StrategyEnum {
+    Addition(AdditionStrategy),
+    Subtraction(SubtractionStrategy),
+    Multiplication(MultiplicationStrategy),
+}
+
+impl Strategy for StrategyEnum {
+    fn execute_strategy(&self, a: i32, b: i32) -> i32 {
+        match self {
+            StrategyEnum::Addition(strategy) => strategy.execute_strategy(a, b),
+            StrategyEnum::Subtraction(strategy) => strategy.execute_strategy(a, b),
+            StrategyEnum::Multiplication(strategy) => strategy.execute_strategy(a, b),
+        }
+    }
+}
+
+/// The built-in strategies by name, so they can be picked from a CLI argument or config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    Sub,
+    Mul,
+}
+
+impl Operation {
+    pub fn strategy(self) -> Box<dyn Strategy> {
+        match self {
+            Operation::Add => Box::new(AdditionStrategy),
+            Operation::Sub => Box::new(SubtractionStrategy),
+            Operation::Mul => Box::new(MultiplicationStrategy),
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Operation::Add => "add",
+            Operation::Sub => "sub",
+            Operation::Mul => "mul",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownOperation {
+    pub name: String,
+}
+
+impl fmt::Display for UnknownOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown operation: {}", self.name)
+    }
+}
+
+impl std::error::Error for UnknownOperation {}
+
+impl FromStr for Operation {
+    type Err = UnknownOperation;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "add" => Ok(Operation::Add),
+            "sub" => Ok(Operation::Sub),
+            "mul" => Ok(Operation::Mul),
+            _ => Err(UnknownOperation {
+                name: s.to_string(),
+            }),
+        }
+    }
+}
+
+type StrategyFn = Box<dyn Fn(i32, i32) -> i32>;
+
+// Strategies registered by name at runtime, for plugin-style setups where adding a struct
+// per strategy would be overkill.
+pub struct StrategyRegistry {
+    strategies: HashMap<String, StrategyFn>,
+}
+
+impl StrategyRegistry {
+    /// A registry with the built-in `"add"` and `"sub"` strategies.
+    pub fn new() -> Self {
+        let mut registry = StrategyRegistry {
+            strategies: HashMap::new(),
+        };
+
+        registry.register("add", |a, b| AdditionStrategy.execute_strategy(a, b));
+        registry.register("sub", |a, b| SubtractionStrategy.execute_strategy(a, b));
+
+        registry
+    }
+
+    pub fn register<F>(&mut self, name: &str, f: F)
+    where
+        F: Fn(i32, i32) -> i32 + 'static,
+    {
+        self.strategies.insert(name.to_string(), Box::new(f));
+    }
+
+    pub fn execute(&self, name: &str, a: i32, b: i32) -> Option<i32> {
+        self.strategies.get(name).map(|strategy| strategy(a, b))
+    }
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Picks one of several strategies in proportion to its weight. The random source is injected
+/// so callers choose the generator and tests can make the choice deterministic.
+pub struct WeightedSelector<R: FnMut() -> u32> {
+    strategies: Vec<(Box<dyn Strategy>, u32)>,
+    rng: R,
+}
+
+impl<R: FnMut() -> u32> WeightedSelector<R> {
+    pub fn new(rng: R) -> Self {
+        WeightedSelector {
+            strategies: Vec::new(),
+            rng,
+        }
+    }
+
+    pub fn add(&mut self, strategy: Box<dyn Strategy>, weight: u32) {
+        self.strategies.push((strategy, weight));
+    }
+
+    /// Runs the chosen strategy, or returns `None` when there is nothing with a weight to choose.
+    pub fn execute(&mut self, a: i32, b: i32) -> Option<i32> {
+        let total: u64 = self
+            .strategies
+            .iter()
+            .map(|(_, weight)| u64::from(*weight))
+            .sum();
+
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = u64::from((self.rng)()) % total;
+
+        for (strategy, weight) in &self.strategies {
+            let weight = u64::from(*weight);
+
+            if roll < weight {
+                return Some(strategy.execute_strategy(a, b));
+            }
+
+            roll -= weight;
+        }
+
+        None
+    }
+}
+
+/// Wraps a strategy and remembers its result for each `(a, b)`, so an expensive strategy only
+/// runs once per distinct input.
+pub struct MemoStrategy<S: Strategy> {
+    inner: S,
+    cache: RefCell<HashMap<(i32, i32), i32>>,
+    hits: Cell<usize>,
+    misses: Cell<usize>,
+}
+
+impl<S: Strategy> MemoStrategy<S> {
+    pub fn new(inner: S) -> Self {
+        MemoStrategy {
+            inner,
+            cache: RefCell::new(HashMap::new()),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        }
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.get()
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses.get()
+    }
+}
+
+impl<S: Strategy> Strategy for MemoStrategy<S> {
+    fn execute_strategy(&self, a: i32, b: i32) -> i32 {
+        if let Some(&result) = self.cache.borrow().get(&(a, b)) {
+            self.hits.set(self.hits.get() + 1);
+            return result;
+        }
+
+        self.misses.set(self.misses.get() + 1);
+        let result = self.inner.execute_strategy(a, b);
+        self.cache.borrow_mut().insert((a, b), result);
+
+        result
+    }
+}
+
+/// Only runs the inner strategy when `guard` accepts the inputs, and returns `default`
+/// otherwise.
+pub struct GuardedStrategy<S: Strategy> {
+    inner: S,
+    guard: fn(i32, i32) -> bool,
+    default: i32,
+}
+
+impl<S: Strategy> GuardedStrategy<S> {
+    pub fn new(inner: S, guard: fn(i32, i32) -> bool, default: i32) -> Self {
+        GuardedStrategy {
+            inner,
+            guard,
+            default,
+        }
+    }
+}
+
+impl<S: Strategy> Strategy for GuardedStrategy<S> {
+    fn execute_strategy(&self, a: i32, b: i32) -> i32 {
+        if (self.guard)(a, b) {
+            self.inner.execute_strategy(a, b)
+        } else {
+            self.default
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    OutOfRange { value: i32, min: i32, max: i32 },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::OutOfRange { value, min, max } => {
+                write!(
+                    f,
+                    "{} is outside the allowed range {}..={}",
+                    value, min, max
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Only hands inputs within `range` to the inner strategy. Since rejecting them needs an
+/// error, it is run through `try_execute` rather than the infallible `Strategy` trait.
+pub struct ValidatingStrategy<S: Strategy> {
+    inner: S,
+    range: RangeInclusive<i32>,
+}
+
+impl<S: Strategy> ValidatingStrategy<S> {
+    pub fn new(inner: S, range: RangeInclusive<i32>) -> Self {
+        ValidatingStrategy { inner, range }
+    }
+
+    pub fn try_execute(&self, a: i32, b: i32) -> Result<i32, ValidationError> {
+        if let Some(&value) = [a, b].iter().find(|value| !self.range.contains(value)) {
+            return Err(ValidationError::OutOfRange {
+                value,
+                min: *self.range.start(),
+                max: *self.range.end(),
+            });
+        }
+
+        Ok(self.inner.execute_strategy(a, b))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqlQueryError {
+    Empty,
+    UnknownKeyword { keyword: String },
+}
+
+impl fmt::Display for SqlQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlQueryError::Empty => write!(f, "query is empty"),
+            SqlQueryError::UnknownKeyword { keyword } => {
+                write!(f, "query starts with unknown keyword: {}", keyword)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SqlQueryError {}
+
+/// A query string that has been checked to be non-empty and to start with a known SQL
+/// keyword, so services never receive arbitrary text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlQuery(String);
+
+impl SqlQuery {
+    const KEYWORDS: [&'static str; 8] = [
+        "SELECT", "INSERT", "UPDATE", "DELETE", "CREATE", "ALTER", "DROP", "WITH",
+    ];
+
+    pub fn new(query: &str) -> Result<Self, SqlQueryError> {
+        let keyword = query
+            .split_whitespace()
+            .next()
+            .ok_or(SqlQueryError::Empty)?;
+
+        if Self::KEYWORDS
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(keyword))
+        {
+            Ok(SqlQuery(query.to_string()))
+        } else {
+            Err(SqlQueryError::UnknownKeyword {
+                keyword: keyword.to_string(),
+            })
+        }
+    }
+
+    /// `SELECT {value};`, keeping the value out of the query structure.
+    pub fn select(value: impl fmt::Display) -> Self {
+        SqlQuery(format!("SELECT {};", value))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SqlQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 pub struct DataService<D: Database> {
     db: D,
 }
@@ -47,9 +404,44 @@ impl<D: Database> DataService<D> {
         DataService { db }
     }
 
-    fn get_data(&self, query: &str) -> String {
-        self.db.query(query)
+    fn get_data(&self, query: SqlQuery) -> String {
+        self.db.query(query.as_str())
     }
+
+    /// Binds `params` to the `?` placeholders of `template` in order, as quoted string literals
+    /// with embedded single quotes doubled. Placeholders without a parameter are left as-is
+    /// and surplus parameters are ignored.
+    pub fn query_params(&self, template: &str, params: &[&str]) -> String {
+        let mut params = params.iter();
+        let mut query = String::with_capacity(template.len());
+
+        for c in template.chars() {
+            let param = if c == '?' { params.next() } else { None };
+
+            match param {
+                Some(param) => {
+                    query.push('\'');
+                    query.push_str(&param.replace('\'', "''"));
+                    query.push('\'');
+                }
+                None => query.push(c),
+            }
+        }
+
+        self.db.query(&query)
+    }
+
+    pub fn batch(&self, queries: &[&str]) -> Vec<String> {
+        queries.iter().map(|query| self.db.query(query)).collect()
+    }
+}
+
+/// The pieces of a `Context::execute` run: what the strategy computed and the query the
+/// database ran with it, as the database reported it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextSnapshot {
+    pub strategy_result: i32,
+    pub query: String,
 }
 
 pub struct Context<S: Strategy, D: Database> {
@@ -66,12 +458,75 @@ impl<S: Strategy, D: Database> Context<S, D> {
     }
 
     pub fn execute(&self, a: i32, b: i32) -> String {
-        let result = self.strategy.execute_strategy(a, b);
+        self.describe(a, b).query
+    }
+
+    pub fn describe(&self, a: i32, b: i32) -> ContextSnapshot {
+        let strategy_result = self.strategy.execute_strategy(a, b);
+
+        ContextSnapshot {
+            strategy_result,
+            query: self
+                .data_service
+                .get_data(SqlQuery::select(strategy_result)),
+        }
+    }
+
+    pub fn query(&self, sql: SqlQuery) -> String {
+        self.data_service.get_data(sql)
+    }
+}
+
+/// A strategy that folds a whole slice into one value rather than combining two.
+pub trait ReduceStrategy {
+    fn reduce(&self, values: &[i32]) -> i32;
+}
+
+/// Sums the values; an empty slice sums to 0.
+pub struct SumReduce;
+
+impl ReduceStrategy for SumReduce {
+    fn reduce(&self, values: &[i32]) -> i32 {
+        values.iter().sum()
+    }
+}
+
+/// The largest value; an empty slice gives `i32::MIN`, the identity of `max`.
+pub struct MaxReduce;
+
+impl ReduceStrategy for MaxReduce {
+    fn reduce(&self, values: &[i32]) -> i32 {
+        values.iter().copied().max().unwrap_or(i32::MIN)
+    }
+}
+
+pub struct ReduceContext<R: ReduceStrategy, D: Database> {
+    reducer: R,
+    data_service: DataService<D>,
+}
+
+impl<R: ReduceStrategy, D: Database> ReduceContext<R, D> {
+    pub fn new(reducer: R, data_service: DataService<D>) -> Self {
+        ReduceContext {
+            reducer,
+            data_service,
+        }
+    }
+
+    pub fn execute(&self, values: &[i32]) -> String {
+        let result = self.reducer.reduce(values);
 
-        self.data_service.get_data(&format!("SELECT {};", result))
+        self.data_service.get_data(SqlQuery::select(result))
     }
 }
 
+/// Wires a strategy and a database into a `Context` and runs it, for tests that only care
+/// about the final output.
+#[cfg(test)]
+pub(crate) fn run_strategy<S: Strategy, D: Database>(strategy: S, db: D, a: i32, b: i32) -> String {
+    Context::new(strategy, DataService::new(db)).execute(a, b)
+}
+
 #[cfg(test)]
 mod test {
     use crate::patterns::strategy_di;
@@ -92,4 +547,300 @@ mod test {
 
         assert_eq!(context.execute(10, 3), "Postgres: SELECT 7;");
     }
+
+    #[test]
+    fn test_batch() {
+        let data_service = strategy_di::DataService::new(strategy_di::PostgresDatabase);
+
+        assert_eq!(
+            data_service.batch(&["SELECT 1;", "SELECT 2;", "SELECT 3;"]),
+            vec![
+                "Postgres: SELECT 1;",
+                "Postgres: SELECT 2;",
+                "Postgres: SELECT 3;"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strategy_registry() {
+        let mut registry = strategy_di::StrategyRegistry::new();
+
+        registry.register("max", |a, b| a.max(b));
+
+        assert_eq!(registry.execute("max", 2, 7), Some(7));
+        assert_eq!(registry.execute("add", 2, 7), Some(9));
+        assert_eq!(registry.execute("divide", 2, 7), None);
+    }
+
+    #[test]
+    fn test_run_strategy() {
+        assert_eq!(
+            strategy_di::run_strategy(
+                strategy_di::AdditionStrategy,
+                strategy_di::MySQLDatabase,
+                2,
+                3
+            ),
+            "MySQL: SELECT 5;"
+        );
+    }
+
+    #[test]
+    fn test_operation_round_trip() {
+        for name in ["add", "sub", "mul"] {
+            let operation = name.parse::<strategy_di::Operation>().unwrap();
+
+            assert_eq!(operation.to_string(), name);
+        }
+
+        assert_eq!(
+            "add".parse::<strategy_di::Operation>(),
+            Ok(strategy_di::Operation::Add)
+        );
+        assert_eq!(
+            strategy_di::Operation::Mul
+                .strategy()
+                .execute_strategy(4, 5),
+            20
+        );
+    }
+
+    #[test]
+    fn test_unknown_operation() {
+        let err = "divide".parse::<strategy_di::Operation>().unwrap_err();
+
+        assert_eq!(err.name, "divide");
+        assert_eq!(err.to_string(), "unknown operation: divide");
+    }
+
+    #[test]
+    fn test_weighted_selector() {
+        let mut selector = strategy_di::WeightedSelector::new(|| 3);
+
+        selector.add(Box::new(strategy_di::AdditionStrategy), 3);
+        selector.add(Box::new(strategy_di::SubtractionStrategy), 1);
+
+        assert_eq!(selector.execute(10, 3), Some(7));
+
+        let mut rolls = [0, 2, 3].into_iter().cycle();
+        let mut selector = strategy_di::WeightedSelector::new(move || rolls.next().unwrap());
+
+        selector.add(Box::new(strategy_di::AdditionStrategy), 3);
+        selector.add(Box::new(strategy_di::SubtractionStrategy), 1);
+
+        assert_eq!(selector.execute(10, 3), Some(13));
+        assert_eq!(selector.execute(10, 3), Some(13));
+        assert_eq!(selector.execute(10, 3), Some(7));
+    }
+
+    #[test]
+    fn test_weighted_selector_without_weights() {
+        let mut selector = strategy_di::WeightedSelector::new(|| 0);
+
+        assert_eq!(selector.execute(1, 2), None);
+
+        selector.add(Box::new(strategy_di::AdditionStrategy), 0);
+
+        assert_eq!(selector.execute(1, 2), None);
+    }
+
+    #[test]
+    fn test_sql_query() {
+        let query = strategy_di::SqlQuery::new("select * from users;").unwrap();
+
+        assert_eq!(query.as_str(), "select * from users;");
+        assert_eq!(strategy_di::SqlQuery::select(5).to_string(), "SELECT 5;");
+    }
+
+    #[test]
+    fn test_invalid_sql_query() {
+        assert_eq!(
+            strategy_di::SqlQuery::new(""),
+            Err(strategy_di::SqlQueryError::Empty)
+        );
+        assert_eq!(
+            strategy_di::SqlQuery::new("   "),
+            Err(strategy_di::SqlQueryError::Empty)
+        );
+        assert_eq!(
+            strategy_di::SqlQuery::new("users; DROP TABLE users;"),
+            Err(strategy_di::SqlQueryError::UnknownKeyword {
+                keyword: String::from("users;")
+            })
+        );
+    }
+
+    #[test]
+    fn test_strategy_enum() {
+        use strategy_di::Strategy;
+
+        let subtract = true;
+        let strategy = if subtract {
+            strategy_di::StrategyEnum::Subtraction(strategy_di::SubtractionStrategy)
+        } else {
+            strategy_di::StrategyEnum::Addition(strategy_di::AdditionStrategy)
+        };
+
+        assert_eq!(strategy.execute_strategy(10, 3), 7);
+        assert_eq!(
+            strategy_di::run_strategy(strategy, strategy_di::PostgresDatabase, 4, 6),
+            "Postgres: SELECT -2;"
+        );
+    }
+
+    #[test]
+    fn test_query_params() {
+        let data_service = strategy_di::DataService::new(strategy_di::MySQLDatabase);
+
+        assert_eq!(
+            data_service.query_params(
+                "SELECT * FROM users WHERE name = ? AND role = ?;",
+                &["alice", "admin"]
+            ),
+            "MySQL: SELECT * FROM users WHERE name = 'alice' AND role = 'admin';"
+        );
+        assert_eq!(
+            data_service.query_params("SELECT ?, ?;", &["only"]),
+            "MySQL: SELECT 'only', ?;"
+        );
+    }
+
+    #[test]
+    fn test_query_params_escaping() {
+        let data_service = strategy_di::DataService::new(strategy_di::PostgresDatabase);
+
+        assert_eq!(
+            data_service.query_params(
+                "SELECT * FROM users WHERE name = ?;",
+                &["o'brien'; DROP TABLE users; --"]
+            ),
+            "Postgres: SELECT * FROM users WHERE name = 'o''brien''; DROP TABLE users; --';"
+        );
+    }
+
+    #[test]
+    fn test_reduce_context() {
+        let sum = strategy_di::ReduceContext::new(
+            strategy_di::SumReduce,
+            strategy_di::DataService::new(strategy_di::MySQLDatabase),
+        );
+        let max = strategy_di::ReduceContext::new(
+            strategy_di::MaxReduce,
+            strategy_di::DataService::new(strategy_di::PostgresDatabase),
+        );
+
+        assert_eq!(sum.execute(&[1, 2, 3, 4]), "MySQL: SELECT 10;");
+        assert_eq!(max.execute(&[3, -1, 7, 2]), "Postgres: SELECT 7;");
+    }
+
+    #[test]
+    fn test_reduce_empty_slice() {
+        use strategy_di::ReduceStrategy;
+
+        assert_eq!(strategy_di::SumReduce.reduce(&[]), 0);
+        assert_eq!(strategy_di::MaxReduce.reduce(&[]), i32::MIN);
+    }
+
+    #[test]
+    fn test_memo_strategy() {
+        use strategy_di::Strategy;
+
+        struct CountingStrategy(std::cell::Cell<u32>);
+
+        impl Strategy for CountingStrategy {
+            fn execute_strategy(&self, a: i32, b: i32) -> i32 {
+                self.0.set(self.0.get() + 1);
+                a + b
+            }
+        }
+
+        let memo = strategy_di::MemoStrategy::new(CountingStrategy(std::cell::Cell::new(0)));
+
+        assert_eq!(memo.execute_strategy(2, 3), 5);
+        assert_eq!((memo.hits(), memo.misses()), (0, 1));
+
+        assert_eq!(memo.execute_strategy(2, 3), 5);
+        assert_eq!((memo.hits(), memo.misses()), (1, 1));
+
+        assert_eq!(memo.execute_strategy(3, 2), 5);
+        assert_eq!((memo.hits(), memo.misses()), (1, 2));
+        assert_eq!(memo.inner.0.get(), 2);
+    }
+
+    #[test]
+    fn test_guarded_strategy() {
+        use strategy_di::Strategy;
+
+        let non_negative =
+            strategy_di::GuardedStrategy::new(strategy_di::SubtractionStrategy, |a, b| a >= b, 0);
+
+        assert_eq!(non_negative.execute_strategy(5, 3), 2);
+        assert_eq!(non_negative.execute_strategy(3, 3), 0);
+    }
+
+    #[test]
+    fn test_guarded_strategy_returns_default() {
+        use strategy_di::Strategy;
+
+        let non_negative =
+            strategy_di::GuardedStrategy::new(strategy_di::SubtractionStrategy, |a, b| a >= b, 0);
+
+        assert_eq!(non_negative.execute_strategy(3, 5), 0);
+        assert_eq!(
+            strategy_di::run_strategy(
+                strategy_di::GuardedStrategy::new(
+                    strategy_di::SubtractionStrategy,
+                    |a, b| a >= b,
+                    -1
+                ),
+                strategy_di::MySQLDatabase,
+                1,
+                2
+            ),
+            "MySQL: SELECT -1;"
+        );
+    }
+
+    #[test]
+    fn test_context_describe() {
+        let context = strategy_di::Context::new(
+            strategy_di::AdditionStrategy,
+            strategy_di::DataService::new(strategy_di::MySQLDatabase),
+        );
+
+        assert_eq!(
+            context.describe(2, 3),
+            strategy_di::ContextSnapshot {
+                strategy_result: 5,
+                query: String::from("MySQL: SELECT 5;"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_validating_strategy() {
+        let percent = strategy_di::ValidatingStrategy::new(strategy_di::AdditionStrategy, 0..=100);
+
+        assert_eq!(percent.try_execute(40, 60), Ok(100));
+        assert_eq!(percent.try_execute(0, 0), Ok(0));
+    }
+
+    #[test]
+    fn test_validating_strategy_rejects_out_of_range() {
+        let percent = strategy_di::ValidatingStrategy::new(strategy_di::AdditionStrategy, 0..=100);
+
+        assert_eq!(
+            percent.try_execute(40, 101),
+            Err(strategy_di::ValidationError::OutOfRange {
+                value: 101,
+                min: 0,
+                max: 100
+            })
+        );
+        assert_eq!(
+            percent.try_execute(-1, 50).unwrap_err().to_string(),
+            "-1 is outside the allowed range 0..=100"
+        );
+    }
 }
@@ -0,0 +1,96 @@
+// Rust has no inheritance, so the "abstract base class" of the classic template method
+// becomes a trait: the skeleton lives in a default method and implementors only fill in the hooks.
+
+pub trait ReportGenerator {
+    fn header(&self) -> String;
+    fn body(&self) -> String;
+    fn footer(&self) -> String;
+
+    /// The template method. Implementors are expected to customise the hooks above
+    /// rather than override this.
+    fn generate(&self) -> String {
+        format!("{}\n{}\n{}", self.header(), self.body(), self.footer())
+    }
+}
+
+pub struct CsvReport {
+    rows: Vec<(String, u32)>,
+}
+
+impl CsvReport {
+    pub fn new(rows: Vec<(String, u32)>) -> Self {
+        CsvReport { rows }
+    }
+}
+
+impl ReportGenerator for CsvReport {
+    fn header(&self) -> String {
+        String::from("name,score")
+    }
+
+    fn body(&self) -> String {
+        self.rows
+            .iter()
+            .map(|(name, score)| format!("{},{}", name, score))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn footer(&self) -> String {
+        format!("# {} rows", self.rows.len())
+    }
+}
+
+pub struct HtmlReport {
+    rows: Vec<(String, u32)>,
+}
+
+impl HtmlReport {
+    pub fn new(rows: Vec<(String, u32)>) -> Self {
+        HtmlReport { rows }
+    }
+}
+
+impl ReportGenerator for HtmlReport {
+    fn header(&self) -> String {
+        String::from("<table>")
+    }
+
+    fn body(&self) -> String {
+        self.rows
+            .iter()
+            .map(|(name, score)| format!("<tr><td>{}</td><td>{}</td></tr>", name, score))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn footer(&self) -> String {
+        String::from("</table>")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::patterns::template_method::{CsvReport, HtmlReport, ReportGenerator};
+
+    fn rows() -> Vec<(String, u32)> {
+        vec![(String::from("alice"), 90), (String::from("bob"), 75)]
+    }
+
+    #[test]
+    fn test_csv_report() {
+        let report = CsvReport::new(rows());
+
+        assert_eq!(report.generate(), "name,score\nalice,90\nbob,75\n# 2 rows");
+    }
+
+    #[test]
+    fn test_html_report() {
+        let report = HtmlReport::new(rows());
+
+        assert_eq!(
+            report.generate(),
+            "<table>\n<tr><td>alice</td><td>90</td></tr>\n<tr><td>bob</td><td>75</td></tr>\n</table>"
+        );
+    }
+}